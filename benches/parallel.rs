@@ -0,0 +1,50 @@
+//! Measures how the search speed scales with the number of threads, for each way of sharing the
+//! search between them. Run with `cargo bench --bench parallel`, optionally followed by `--`, the
+//! milliseconds to search for in each run and the largest thread count to try.
+
+extern crate tictactoe;
+extern crate num_cpus;
+
+use std::env;
+use std::time::Duration;
+
+use tictactoe::game::TicTacToe;
+use tictactoe::ai::{ Ai, Limits, Parallelism, Ponder, Request, Response, Settings };
+
+fn main() {
+	// `cargo bench` passes `--bench` to benches without the test harness
+	let args: Vec<u64> = env::args().skip(1).filter_map(|arg| arg.parse().ok()).collect();
+	let time = args.first().cloned().unwrap_or(3000);
+	let max_threads = args.get(1).map(|&n| n as usize).unwrap_or_else(num_cpus::get);
+
+	let mut thread_counts = vec![1];
+	while thread_counts.last().unwrap() * 2 <= max_threads {
+		let n = thread_counts.last().unwrap() * 2;
+		thread_counts.push(n);
+	}
+	if *thread_counts.last().unwrap() != max_threads {
+		thread_counts.push(max_threads);
+	}
+
+	println!("{:>8} {:>8} {:>12} {:>12} {:>6}", "mode", "threads", "nodes/s", "sims/s", "depth");
+	for &parallelism in &[Parallelism::Leaf, Parallelism::Tree, Parallelism::Root] {
+		for &threads in &thread_counts {
+			let ai = Ai::with_settings(TicTacToe::new(), Settings {
+				threads: Some(threads),
+				parallelism,
+				..Settings::default()
+			});
+			ai.send(Request::SetPonder(Ponder::Off));
+			ai.send(Request::Go { limits: Limits { time: Some(Duration::from_millis(time)), ..Limits::default() } });
+
+			loop {
+				if let Response::Done { sims, nodes, depth, time_elapsed, .. } = ai.recv_blocking() {
+					let secs = time_elapsed.as_secs() as f64 + time_elapsed.subsec_nanos() as f64 / 1e9;
+					println!("{:>8} {:>8} {:>12.0} {:>12.0} {:>6}",
+						format!("{:?}", parallelism).to_lowercase(), threads, nodes as f64 / secs, sims as f64 / secs, depth);
+					break;
+				}
+			}
+		}
+	}
+}
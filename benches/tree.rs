@@ -0,0 +1,195 @@
+//! Measures how fast the search tree's storage grows and frees nodes: a single-threaded search to
+//! a fixed number of nodes, then re-rooting the tree after a move, which frees the other moves'
+//! subtrees, and finally starting again from a new position, which frees everything. Run with
+//! `cargo bench --bench tree`, optionally followed by `--` and the number of nodes to grow.
+//!
+//! The same steps are then timed on their own for the slot arenas the tree is stored in, through
+//! the crate's hidden `NodeStore`, and for the sharded `HashMap`s they replaced, which freed
+//! subtrees recursively, growing a random tree of the same size in each. The `HashMap` storage is
+//! no longer in the crate, so `HashMapStore` is a model of it, with nodes of roughly the same size
+//! as the search tree's.
+
+extern crate rand;
+extern crate tictactoe;
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+
+use rand::{ Rng, SeedableRng, XorShiftRng };
+
+use tictactoe::game::TicTacToe;
+use tictactoe::ai::{ Ai, Limits, NodeStore, Parallelism, Request, Response, Settings };
+
+/// Shards of `HashMapStore`, as in the search tree.
+const SHARDS: usize = 64;
+
+/// A tree node with roughly the contents of the search tree's nodes.
+struct Node {
+	_game: TicTacToe,
+	_parent: Option<usize>,
+	_stats: (u32, f64, u32),
+	children: HashMap<usize, usize>,
+}
+
+impl Node {
+	fn new(parent: Option<usize>) -> Node {
+		Node { _game: TicTacToe::new(), _parent: parent, _stats: (0, 0.0, 0), children: HashMap::new() }
+	}
+}
+
+/// The storage of a tree's nodes.
+trait Store {
+	fn add_root(&self) -> usize;
+	/// Adds a child of `parent` under a key that `parent` has no child for yet.
+	fn add_child(&self, parent: usize, key: usize) -> usize;
+	/// Frees a node and everything below it.
+	fn free(&self, id: usize);
+	fn children(&self, id: usize) -> Vec<usize>;
+}
+
+/// A model of the storage from before the slot arenas, which is no longer in the crate: node IDs
+/// are counted up and kept in `HashMap`s.
+struct HashMapStore {
+	shards: Vec<Mutex<HashMap<usize, Node>>>,
+	next_id: Mutex<usize>,
+}
+
+impl HashMapStore {
+	fn add(&self, node: Node) -> usize {
+		let id = {
+			let mut next_id = self.next_id.lock().unwrap();
+			*next_id += 1;
+			*next_id - 1
+		};
+		self.shards[id % SHARDS].lock().unwrap().insert(id, node);
+		id
+	}
+}
+
+impl Store for HashMapStore {
+	fn add_root(&self) -> usize {
+		self.add(Node::new(None))
+	}
+
+	fn add_child(&self, parent: usize, key: usize) -> usize {
+		let id = self.add(Node::new(Some(parent)));
+		self.shards[parent % SHARDS].lock().unwrap().get_mut(&parent).unwrap().children.insert(key, id);
+		id
+	}
+
+	fn free(&self, id: usize) {
+		let removed = self.shards[id % SHARDS].lock().unwrap().remove(&id);
+		if let Some(node) = removed {
+			for &child in node.children.values() {
+				self.free(child);
+			}
+		}
+	}
+
+	fn children(&self, id: usize) -> Vec<usize> {
+		self.shards[id % SHARDS].lock().unwrap()[&id].children.values().cloned().collect()
+	}
+}
+
+/// The storage the search tree uses. The keys only have to be distinct, so they don't need to be
+/// legal moves.
+impl Store for NodeStore<TicTacToe> {
+	fn add_root(&self) -> usize {
+		NodeStore::add_root(self, TicTacToe::new())
+	}
+
+	fn add_child(&self, parent: usize, key: usize) -> usize {
+		NodeStore::add_child(self, parent, (key, 0), TicTacToe::new())
+	}
+
+	fn free(&self, id: usize) {
+		NodeStore::free(self, id);
+	}
+
+	fn children(&self, id: usize) -> Vec<usize> {
+		NodeStore::children(self, id)
+	}
+}
+
+/// Grows a random tree of `nodes` nodes in `store`, re-roots it at one of the root's children by
+/// freeing the others, then frees the new root, printing how long each step took.
+fn time_store<S: Store>(name: &str, store: &S, nodes: usize) {
+	let mut rand = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+	let start = Instant::now();
+	let root = store.add_root();
+	let mut ids = vec![root];
+	while ids.len() < nodes {
+		// earlier nodes get more children, as they do in a search
+		let parent = ids[rand.gen_range(0, ids.len()) / 2];
+		let key = ids.len();
+		ids.push(store.add_child(parent, key));
+	}
+	let grow = start.elapsed();
+
+	// the root's first child is kept, whichever order the store gives the children in
+	let kept = ids[1];
+	let others: Vec<usize> = store.children(root).into_iter().filter(|&id| id != kept).collect();
+	let start = Instant::now();
+	for child in others {
+		store.free(child);
+	}
+	let reroot = start.elapsed();
+
+	let start = Instant::now();
+	store.free(kept);
+	let free = start.elapsed();
+
+	println!("{}: grew {} nodes in {:.3}s, re-rooted in {:.3}ms, freed the rest in {:.3}ms",
+		name, nodes, secs(grow), secs(reroot) * 1000.0, secs(free) * 1000.0);
+}
+
+fn secs(time: Duration) -> f64 {
+	time.as_secs() as f64 + time.subsec_nanos() as f64 / 1e9
+}
+
+/// Waits for the answer to a `MakeMove` or `SetPosition`, returning how long it took.
+fn time_ok(ai: &Ai<TicTacToe>, start: Instant) -> Duration {
+	while let Response::Info { .. } | Response::Done { .. } = ai.recv_blocking() {}
+	start.elapsed()
+}
+
+fn main() {
+	// `cargo bench` passes `--bench` to benches without the test harness
+	let nodes = env::args().skip(1).filter_map(|arg| arg.parse().ok()).next().unwrap_or(1_000_000);
+
+	// tree parallelism grows a node every few playouts, where leaf parallelism would spend a whole
+	// batch of playouts on each one
+	let ai = Ai::with_settings(TicTacToe::new(), Settings {
+		seed: Some(1),
+		parallelism: Parallelism::Tree,
+		..Settings::default()
+	});
+
+	ai.send(Request::Go { limits: Limits { nodes: Some(nodes), ..Limits::default() } });
+	let (best_move, grown, time) = loop {
+		if let Response::Done { best_move, nodes, time_elapsed, .. } = ai.recv_blocking() {
+			break (best_move.unwrap(), nodes, time_elapsed);
+		}
+	};
+	println!("grew {} nodes in {:.3}s ({:.0} nodes/s)", grown, secs(time), grown as f64 / secs(time));
+
+	let start = Instant::now();
+	ai.make_move(best_move);
+	let time = time_ok(&ai, start);
+	println!("re-rooted after {:?} in {:.3}ms", best_move, secs(time) * 1000.0);
+
+	let start = Instant::now();
+	ai.send(Request::SetPosition(TicTacToe::new()));
+	let time = time_ok(&ai, start);
+	println!("freed the rest in {:.3}ms", secs(time) * 1000.0);
+	drop(ai);
+
+	time_store("HashMap", &HashMapStore {
+		shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+		next_id: Mutex::new(0),
+	}, nodes);
+	time_store("arena", &NodeStore::default(), nodes);
+}
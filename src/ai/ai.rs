@@ -1,179 +1,1121 @@
-use std::collections::HashMap;
-use std::cell::{ RefCell, Ref, RefMut };
-use std::thread;
+use std::collections::{ HashMap, HashSet };
+use std::hash::Hash;
+use std::mem;
+use std::f64::consts::SQRT_2;
+use std::ops::{ Deref, DerefMut };
+use std::thread::{ self, JoinHandle };
+use std::sync::{ Arc, Mutex, MutexGuard };
+use std::sync::atomic::{ AtomicUsize, Ordering };
 use std::sync::mpsc::{ channel, Sender, Receiver, TryRecvError };
 use std::time::{ Duration, Instant };
 
-use super::Game;
+use super::{ Game, GameResult };
 use super::tree::*;
 use ai::montecarlo::montecarlo;
-use ai::sim_thread_pool::SimThreadPool;
+use ai::sim_thread_pool::{ SimThreadPool, SimBudget };
+use ai::tree_parallel::TreeWorkers;
+use ai::root_parallel::RootWorkers;
+use ai::random::new_rng;
+use ai::solver::{ solve, Solution };
+use num_cpus;
+use rand::XorShiftRng;
 
+/// How long each batch of simulations runs for when there is no simulation limit.
+const SIM_TIME: u64 = 25; // milliseconds
+/// The most simulations run in a single batch when searching with a simulation limit.
+const SIM_BATCH: u64 = 1024;
+/// The most positions the endgame solver searches before leaving a position to the tree search.
+const ENDGAME_NODES: u64 = 100_000;
+
+/// Limits on how much searching a `Request::Go` does. The search ends as soon as any of the
+/// limits is reached; a search without any limits only ends on `Request::Stop`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    /// Wall-clock time to search for.
+    pub time: Option<Duration>,
+    /// Number of random playouts to run.
+    pub sims: Option<u64>,
+    /// Number of nodes to add to the tree.
+    pub nodes: Option<usize>,
+    /// Depth below the root that the tree has to reach.
+    pub depth: Option<usize>,
+}
+
+/// How the random playouts that estimate the value of a position choose their moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rollout {
+    /// Every move is chosen uniformly at random.
+    Random,
+    /// A move that wins the game straight away is always played, otherwise moves are random.
+    /// Playouts are slower but less noisy.
+    WinFirst,
+}
+
+/// How the search is spread over several threads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parallelism {
+    /// One thread grows the tree and the others only run the playouts from the leaf it picks.
+    Leaf,
+    /// Every thread grows the same tree, selecting, expanding and simulating on its own. Moves
+    /// that other threads are busy with count as losses so that the threads spread out.
+    Tree,
+    /// Every thread grows a tree of its own from the same root, and only the statistics of the
+    /// moves from the root are combined.
+    Root,
+}
+
+/// What the search does once the tree has as many nodes as `Settings` allow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FullTree {
+    /// Stop adding nodes and only refine the statistics of the ones already in the tree.
+    Stop,
+    /// Free everything below the least visited nodes, until the tree is back to three quarters of
+    /// its limit, and keep growing.
+    Prune,
+}
+
+/// How an `Ai` searches, fixed when it is created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Makes the AI deterministic; see `Ai`.
+    pub seed: Option<u64>,
+    /// Number of simulation threads. Defaults to one per CPU, or to one with a seed.
+    pub threads: Option<usize>,
+    /// How strongly the search favours moves it has tried less often (the `C` of UCT).
+    pub exploration: f64,
+    pub rollout: Rollout,
+    /// Tree parallelism becomes root parallelism when the AI has a seed and several threads.
+    pub parallelism: Parallelism,
+    /// The most nodes the tree grows to. With root parallelism every thread's tree has this limit.
+    pub max_nodes: Option<usize>,
+    /// Roughly the most memory, in bytes, that the tree (or with root parallelism, all the trees
+    /// together) may use, which limits the number of nodes like `max_nodes`.
+    pub max_memory: Option<usize>,
+    pub full_tree: FullTree,
+    /// Whether a position reached by different move orders shares a single node, and so its
+    /// statistics, for games that provide `Game::hash_key`.
+    pub transpositions: bool,
+    /// A position with at most this many moves left in the game (`Game::moves_left`) is first
+    /// solved exactly, which replaces the tree search if it takes no more than `ENDGAME_NODES`
+    /// positions. 0 turns the solver off.
+    pub endgame_moves: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            seed: None,
+            threads: None,
+            exploration: SQRT_2,
+            rollout: Rollout::Random,
+            parallelism: Parallelism::Tree,
+            max_nodes: None,
+            max_memory: None,
+            full_tree: FullTree::Prune,
+            transpositions: false,
+            endgame_moves: 16,
+        }
+    }
+}
+
+impl Settings {
+    /// Sets one setting from its name and value as text, as the front ends take them: `seed`,
+    /// `threads`, `exploration`, `rollout` (`random` or `win-first`), `parallelism` (`tree`,
+    /// `root` or `leaf`), `max-nodes`, `memory` (in megabytes), `full-tree` (`stop` or `prune`),
+    /// `transpositions` (`on` or `off`) and `endgame-moves`.
+    ///
+    /// An error says what was wrong after the setting's name, e.g. `expects a whole number`, so
+    /// that the caller can put whatever the setting was called in front of it.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "seed" => {
+                self.seed = Some(value.parse().map_err(|_| "expects a whole number".to_owned())?);
+            },
+            "threads" => {
+                self.threads = match value.parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err("expects a whole number above 0".to_owned()),
+                };
+            },
+            "exploration" => {
+                self.exploration = match value.parse::<f64>() {
+                    Ok(c) if c >= 0.0 && c.is_finite() => c,
+                    _ => return Err("expects a number of at least 0".to_owned()),
+                };
+            },
+            "rollout" => {
+                self.rollout = match value {
+                    "random" => Rollout::Random,
+                    "win-first" => Rollout::WinFirst,
+                    _ => return Err("expects random or win-first".to_owned()),
+                };
+            },
+            "parallelism" => {
+                self.parallelism = match value {
+                    "tree" => Parallelism::Tree,
+                    "root" => Parallelism::Root,
+                    "leaf" => Parallelism::Leaf,
+                    _ => return Err("expects tree, root or leaf".to_owned()),
+                };
+            },
+            "max-nodes" => {
+                self.max_nodes = match value.parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err("expects a whole number above 0".to_owned()),
+                };
+            },
+            "memory" => {
+                self.max_memory = match value.parse::<usize>() {
+                    Ok(mb) if mb > 0 => Some(mb * 1024 * 1024),
+                    _ => return Err("expects a whole number of megabytes above 0".to_owned()),
+                };
+            },
+            "full-tree" => {
+                self.full_tree = match value {
+                    "stop" => FullTree::Stop,
+                    "prune" => FullTree::Prune,
+                    _ => return Err("expects stop or prune".to_owned()),
+                };
+            },
+            "transpositions" => {
+                self.transpositions = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err("expects on or off".to_owned()),
+                };
+            },
+            "endgame-moves" => {
+                self.endgame_moves = value.parse().map_err(|_| "expects a whole number".to_owned())?;
+            },
+            _ => return Err("is not a setting".to_owned()),
+        }
+
+        Ok(())
+    }
+}
+
+/// What the AI does while it isn't running a search for `Request::Go`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ponder {
+    /// Wait until the next `Request::Go`.
+    Off,
+    /// Keep searching from the current position.
+    On,
+    /// Search from each new position until one of the limits is reached, then wait. A
+    /// `Request::Go` takes over from the pondering on its position.
+    Limited(Limits),
+}
+
+/// How much of the search tree survived the last change of position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeReuse {
+    /// Nodes in the tree before the position changed.
+    pub nodes_before: usize,
+    /// Nodes below the new position, which make up the new tree.
+    pub nodes_kept: usize,
+    /// Simulations that had already been run through the new position.
+    pub sims_kept: u64,
+}
+
+impl TreeReuse {
+    /// The fraction of the old tree's nodes that were kept.
+    pub fn fraction(&self) -> f64 {
+        if self.nodes_before == 0 {
+            0.0
+        } else {
+            self.nodes_kept as f64 / self.nodes_before as f64
+        }
+    }
+}
+
+/// A message sent to the AI thread.
 #[derive(Debug)]
 pub enum Request<G: Game> {
     Info,
 
     MakeMove(G::Move),
+    /// Takes back the last move made with `Request::MakeMove`, going back to the tree from before
+    /// it. Does nothing if there is no move to take back. Answered with `Response::Ok`.
+    Undo,
+    /// Starts again from a new position with an empty tree, forgetting the moves made so far.
+    /// Answered with `Response::Ok`.
+    SetPosition(G),
+
+    /// Starts a search which ends with a `Response::Done` once one of the limits is reached.
+    /// Making a move abandons the search without a response.
+    Go { limits: Limits },
+    /// Ends the current search early.
+    Stop,
+
+    /// Stops all searching, including any search started by `Request::Go`, until
+    /// `Request::Resume`. Other requests are still answered while paused.
+    Pause,
+    Resume,
+    /// Changes what the AI does between searches. AIs start with `Ponder::On`, unless they were
+    /// created with a seed, in which case they start with `Ponder::Off`.
+    SetPonder(Ponder),
+    /// Shuts down the AI thread. Dropping the `Ai` does this automatically.
+    Quit,
+}
+
+/// A game-theoretic result that the search has proven, for the player it is given for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Proof {
+    Win,
+    Draw,
+    Loss,
 }
 
+impl Proof {
+    /// The proof of `game` for `player` if it is over, which is how a move that ends the game is
+    /// proven straight away.
+    pub(in super) fn of_game<G: Game>(game: &G, player: &G::Player) -> Option<Self> {
+        game.get_result().map(|result| match result {
+            GameResult::Win(ref winner) if winner == player => Proof::Win,
+            GameResult::Win(_) => Proof::Loss,
+            GameResult::Draw => Proof::Draw,
+        })
+    }
+
+    /// The same result for the other player.
+    pub(in super) fn flip(self) -> Self {
+        match self {
+            Proof::Win => Proof::Loss,
+            Proof::Draw => Proof::Draw,
+            Proof::Loss => Proof::Win,
+        }
+    }
+
+    /// Scores the result the same way as `GameResult::score`.
+    pub(in super) fn score(self) -> f64 {
+        match self {
+            Proof::Win => 1.0,
+            Proof::Draw => 0.5,
+            Proof::Loss => 0.0,
+        }
+    }
+
+    /// Scores the result for `player`, when it was proven for `prover`.
+    pub(in super) fn score_for<P: PartialEq>(self, prover: &P, player: &P) -> f64 {
+        if player == prover { self.score() } else { self.flip().score() }
+    }
+}
+
+/// A message sent back from the AI thread.
 #[derive(Debug)]
 pub enum Response<G: Game> {
     Info {
         best_move: Option<G::Move>,
         confidence: f64,
+        /// The result of the position for the player to move, once the search has proven it.
+        proof: Option<Proof>,
         total_sims: u64,
         time_elapsed: Duration,
+        /// `None` until the position first changes.
+        tree_reuse: Option<TreeReuse>,
+        /// Nodes in the search tree, or with root parallelism in all the trees.
+        nodes: usize,
+        /// An estimate of the memory those nodes use, in bytes.
+        memory: usize,
     },
 
     Ok,
+
+    /// Sent when a search started by `Request::Go` ends. `sims`, `nodes` and `time_elapsed`
+    /// only count the work done during that search.
+    Done {
+        best_move: Option<G::Move>,
+        confidence: f64,
+        sims: u64,
+        nodes: usize,
+        depth: usize,
+        time_elapsed: Duration,
+    },
+}
+
+struct Search {
+    limits: Limits,
+    start_time: Instant,
+    start_sims: u64,
+    start_nodes: usize,
+    depth: usize,
+}
+
+impl Search {
+    fn new(limits: Limits, num_sims: u64, num_nodes: usize) -> Self {
+        Search {
+            limits,
+            start_time: Instant::now(),
+            start_sims: num_sims,
+            start_nodes: num_nodes,
+            depth: 0,
+        }
+    }
+
+    fn finished(&self, num_sims: u64, num_nodes: usize) -> bool {
+        let l = &self.limits;
+
+        l.time.map(|t| self.start_time.elapsed() >= t).unwrap_or(false)
+            || l.sims.map(|n| num_sims.saturating_sub(self.start_sims) >= n).unwrap_or(false)
+            || l.nodes.map(|n| num_nodes.saturating_sub(self.start_nodes) >= n).unwrap_or(false)
+            || l.depth.map(|d| self.depth >= d).unwrap_or(false)
+    }
+
+}
+
+fn is_pondering(ponder: &Ponder, ponder_search: &Option<Search>) -> bool {
+    match *ponder {
+        Ponder::Off => false,
+        Ponder::On => true,
+        Ponder::Limited(_) => ponder_search.is_some(),
+    }
+}
+
+/// How many simulations to run in the next batch. Deterministic AIs always run a fixed number so
+/// that the tree doesn't depend on how fast the simulations are.
+fn budget(search: Option<&Search>, num_sims: u64, deterministic: bool) -> SimBudget {
+    match search.and_then(|s| s.limits.sims.map(|n| n.saturating_sub(num_sims - s.start_sims))) {
+        // a search always runs at least one iteration, so that it has a move to answer with
+        Some(remaining) => SimBudget::Count(remaining.clamp(1, SIM_BATCH) as u32),
+        None if deterministic => SimBudget::Count(SIM_BATCH as u32),
+        None => SimBudget::Time(SIM_TIME),
+    }
 }
 
 pub type NodeID = usize;
 
+/// The number of separately locked parts the tree is stored in, so that tree-parallel workers
+/// rarely have to wait for each other.
+pub(in super) const SHARDS: usize = 64;
+
+/// The fraction of its node limit that a full tree is pruned back to.
+const PRUNE_TO: f64 = 0.75;
+
+/// Roughly how much memory a node takes: its slot, plus its entry in its parent's children.
+fn node_bytes<G: Game>() -> usize {
+    // hash maps keep at least one in eight of their entries free, plus a control byte per entry
+    mem::size_of::<Option<MoveTreeNode<G>>>() + (mem::size_of::<(G::Move, Child)>() + 1) * 8 / 7
+}
+
+/// One part of the tree's storage. Nodes live in `slots`, and the slots of freed nodes are kept in
+/// `free` to be reused before the storage grows.
+struct Shard<G: Game> {
+    slots: Vec<Option<MoveTreeNode<G>>>,
+    free: Vec<usize>,
+}
+
+/// A node borrowed from a `NodeList`, which keeps the shard holding it locked.
+pub(in super) struct NodeRef<'a, G: Game + 'a> {
+    shard: MutexGuard<'a, Shard<G>>,
+    slot: usize,
+}
+
+impl<'a, G: Game> Deref for NodeRef<'a, G> {
+    type Target = MoveTreeNode<G>;
+
+    fn deref(&self) -> &MoveTreeNode<G> {
+        self.shard.slots[self.slot].as_ref().expect("node was freed")
+    }
+}
+
+impl<'a, G: Game> DerefMut for NodeRef<'a, G> {
+    fn deref_mut(&mut self) -> &mut MoveTreeNode<G> {
+        self.shard.slots[self.slot].as_mut().expect("node was freed")
+    }
+}
+
+/// The nodes of the search tree, which can be shared between threads. A thread must not borrow
+/// a node while it holds another, since both may be in the same shard.
+///
+/// Nodes are added to the shards in turn, and a node's ID is its slot times `SHARDS` plus its
+/// shard. IDs are reused once their node is freed.
 pub(in super) struct NodeList<G: Game> {
-    nodes: HashMap<NodeID, RefCell<MoveTreeNode<G>>>,
-    next_id: NodeID,
+    shards: Vec<Mutex<Shard<G>>>,
+    next_shard: AtomicUsize,
+    len: AtomicUsize,
+    /// Every node ever added, which unlike `len` never goes down.
+    added: AtomicUsize,
+    max_nodes: Option<usize>,
+    /// The node for each position by its `Game::hash_key`, when transposed positions share a node.
+    table: Option<Mutex<HashMap<u64, NodeID>>>,
 }
 impl<G: Game> NodeList<G> {
-    pub fn new() -> Self {
+    /// Creates an empty tree, which stops growing new leaves once it holds `max_nodes` nodes. With
+    /// `transpositions`, a position reached by different moves gets a single node, turning the tree
+    /// into a directed acyclic graph.
+    pub fn new(max_nodes: Option<usize>, transpositions: bool) -> Self {
         NodeList {
-            nodes: HashMap::new(),
-            next_id: 0,
+            shards: (0..SHARDS).map(|_| Mutex::new(Shard { slots: Vec::new(), free: Vec::new() })).collect(),
+            next_shard: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            added: AtomicUsize::new(0),
+            max_nodes,
+            table: if transpositions { Some(Mutex::new(HashMap::new())) } else { None },
         }
     }
 
-    pub fn add(&mut self, node: MoveTreeNode<G>) -> NodeID {
-        let id = self.next_id;
-        self.nodes.insert(id, RefCell::new(node));
-        self.next_id += 1;
+    /// Creates an empty tree with the same settings.
+    pub fn new_like(&self) -> Self {
+        NodeList::new(self.max_nodes, self.table.is_some())
+    }
+
+    fn table(&self) -> Option<MutexGuard<'_, HashMap<u64, NodeID>>> {
+        self.table.as_ref().map(|table| table.lock().expect("a search thread panicked"))
+    }
+
+    fn shard(&self, node: NodeID) -> MutexGuard<'_, Shard<G>> {
+        self.shards[node % SHARDS].lock().expect("a search thread panicked")
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// The number of nodes added since the tree was created, including freed ones.
+    pub fn added(&self) -> usize {
+        self.added.load(Ordering::Relaxed)
+    }
+
+    /// Whether the tree has reached its node limit, after which the search simulates from leaves
+    /// instead of expanding them. Nodes can still be added, e.g. for the moves actually played.
+    pub fn is_full(&self) -> bool {
+        self.max_nodes.map(|max| self.len() >= max).unwrap_or(false)
+    }
+
+    /// Removes every node, keeping the storage for the next tree.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            let mut shard = shard.lock().expect("a search thread panicked");
+            shard.slots.clear();
+            shard.free.clear();
+        }
+        if let Some(mut table) = self.table() {
+            table.clear();
+        }
+        self.len.store(0, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, node: MoveTreeNode<G>) -> NodeID {
+        let index = self.next_shard.fetch_add(1, Ordering::Relaxed) % SHARDS;
+        let mut shard = self.shards[index].lock().expect("a search thread panicked");
+
+        let slot = match shard.free.pop() {
+            Some(slot) => {
+                shard.slots[slot] = Some(node);
+                slot
+            },
+            None => {
+                shard.slots.push(Some(node));
+                shard.slots.len() - 1
+            },
+        };
+        self.len.fetch_add(1, Ordering::Relaxed);
+        self.added.fetch_add(1, Ordering::Relaxed);
+
+        slot * SHARDS + index
+    }
+
+    /// Adds the node for `game` as a child of `parent`, or with transpositions, finds the node
+    /// that is already in the tree for it. The caller must not be holding `parent`.
+    pub fn add_child(&self, game: G, parent: NodeID) -> NodeID {
+        // the table is locked until the node is in it, so that two threads can't both add it
+        let mut table = self.table();
+        let key = table.as_ref().and_then(|_| game.hash_key());
+
+        if let (Some(table), Some(key)) = (table.as_ref(), key) {
+            if let Some(&id) = table.get(&key) {
+                self.get(id).refs += 1;
+                return id;
+            }
+        }
+
+        let mut node = MoveTreeNode::new(game, parent);
+        node.refs = 1;
+        let id = self.add(node);
+
+        if let (Some(table), Some(key)) = (table.as_mut(), key) {
+            table.insert(key, id);
+        }
 
         id
     }
 
-    pub fn get(&self, node: NodeID) -> Ref<MoveTreeNode<G>> {
-        self.nodes[&node].borrow()
+    pub fn get(&self, node: NodeID) -> NodeRef<'_, G> {
+        NodeRef {
+            shard: self.shard(node),
+            slot: node / SHARDS,
+        }
     }
 
-    pub fn get_mut(&self, node: NodeID) -> RefMut<MoveTreeNode<G>> {
-        self.nodes[&node].borrow_mut()
+    /// Takes a node out of the tree, freeing its slot.
+    fn free(&self, node_id: NodeID) -> Option<MoveTreeNode<G>> {
+        let slot = node_id / SHARDS;
+        let node = {
+            let mut shard = self.shard(node_id);
+
+            let node = shard.slots.get_mut(slot).and_then(|node| node.take());
+            if node.is_some() {
+                shard.free.push(slot);
+                self.len.fetch_sub(1, Ordering::Relaxed);
+            }
+            node
+        };
+
+        // the slot may be reused, so the position mustn't lead to it any more
+        if let (Some(mut table), Some(key)) = (self.table(), node.as_ref().and_then(|node| node.game.hash_key())) {
+            if table.get(&key) == Some(&node_id) {
+                table.remove(&key);
+            }
+        }
+
+        node
     }
 
-    pub fn drop_node(&mut self, node_id: NodeID, except: NodeID) {
+    /// Drops one reference to a node, freeing it and everything below it that isn't reached some
+    /// other way, apart from the subtree of `except`.
+    pub fn drop_node(&self, node_id: NodeID, except: NodeID) {
         // Note: it is up to the caller to ensure that the node being removed is not referenced
         //   by any other node (i.e. its parent)
-        if let Some(node) = self.nodes.remove(&node_id) {
-            for ref child in node.borrow().children.values() {
-                if child.node != except {
-                    self.drop_node(child.node, except);
+        let mut stack = vec![node_id];
+
+        while let Some(id) = stack.pop() {
+            let shared = match self.shard(id).slots.get_mut(id / SHARDS).and_then(|node| node.as_mut()) {
+                Some(ref mut node) if node.refs > 1 => {
+                    node.refs -= 1;
+                    true
+                },
+                _ => false,
+            };
+
+            if !shared {
+                if let Some(node) = self.free(id) {
+                    stack.extend(node.children.values().map(|child| child.node).filter(|&child| child != except));
                 }
             }
         }
     }
 
-    pub fn take(&mut self, node_id: NodeID) -> MoveTreeNode<G> {
-        self.nodes.remove(&node_id).unwrap().into_inner()
+    /// Drops everything below a node, leaving it as a leaf.
+    pub fn prune(&self, node_id: NodeID) {
+        let children: Vec<NodeID> = self.get(node_id).children.drain().map(|(_, child)| child.node).collect();
+
+        for child in children {
+            self.drop_node(child, node_id);
+        }
+    }
+
+    /// Every node below and including `node_id`, once each.
+    fn subtree(&self, node_id: NodeID) -> Vec<NodeID> {
+        let mut nodes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![node_id];
+
+        while let Some(id) = stack.pop() {
+            // only transpositions can reach a node twice
+            if self.table.is_some() && !seen.insert(id) {
+                continue;
+            }
+
+            nodes.push(id);
+            stack.extend(self.get(id).children.values().map(|child| child.node));
+        }
+
+        nodes
+    }
+
+    /// The number of nodes in the tree below and including a node.
+    pub fn subtree_size(&self, node_id: NodeID) -> usize {
+        self.subtree(node_id).len()
+    }
+
+    /// Prunes the least visited nodes below `root` until the tree is back to `PRUNE_TO` of its
+    /// limit. `root` itself is never pruned, so its moves keep their statistics.
+    pub fn shrink(&self, root: NodeID) {
+        let target = match self.max_nodes {
+            Some(max) => (max as f64 * PRUNE_TO) as usize,
+            None => return,
+        };
+
+        let mut candidates: Vec<(u32, NodeID)> = self.subtree(root).into_iter()
+            .filter(|&id| id != root)
+            .filter_map(|id| {
+                let node = self.get(id);
+                if node.children.is_empty() { None } else { Some((node.games, id)) }
+            })
+            .collect();
+
+        // a node never has more games than its parent, so nodes tend to be pruned before their
+        // ancestors, but a node may already have gone with one of them
+        candidates.sort();
+        for (_, id) in candidates {
+            if self.len() <= target {
+                break;
+            }
+            if self.contains(id) {
+                self.prune(id);
+            }
+        }
+    }
+
+    fn contains(&self, node_id: NodeID) -> bool {
+        self.shard(node_id).slots.get(node_id / SHARDS).map(|node| node.is_some()).unwrap_or(false)
     }
 
-    pub fn restore(&mut self, node_id: NodeID, node: MoveTreeNode<G>) {
-        self.nodes.insert(node_id, RefCell::new(node));
+    /// Makes a move from `root`, returning the node for the new position and whether it was
+    /// already in the tree. The old root stays as the new root's parent so the move can be taken
+    /// back, but the other moves' subtrees are only kept one level deep.
+    pub fn advance(&self, root: NodeID, mv: &G::Move) -> (NodeID, bool) {
+        let existing = self.get(root).children.get(mv).map(|v| v.node);
+
+        let new_root = match existing {
+            Some(id) => {
+                // a transposed node remembers the parent it was first added under
+                self.get(id).parent = Some(root);
+                id
+            },
+            None => {
+                let game = self.get(root).game.make_move(mv).expect("AI was asked to make an invalid move");
+                self.add(MoveTreeNode::new(*game, root))
+            },
+        };
+
+        let others: Vec<NodeID> = self.get(root).children.values()
+            .map(|child| child.node)
+            .filter(|&id| id != new_root)
+            .collect();
+        for id in others {
+            self.prune(id);
+        }
+
+        (new_root, existing.is_some())
+    }
+
+    /// Takes back the move that led to `root`, returning the previous root, if there is one.
+    pub fn retreat(&self, root: NodeID) -> Option<NodeID> {
+        let parent = self.get(root).parent;
+
+        if let Some(prev_root) = parent {
+            // a move the tree hadn't expanded yet isn't part of the old tree
+            if !self.get(prev_root).children.values().any(|child| child.node == root) {
+                self.drop_node(root, prev_root);
+            }
+        }
+
+        parent
     }
 }
 
-fn best_move<G: Game>(nodes: &NodeList<G>, root: NodeID) -> Option<G::Move> {
-    let rt = nodes.get(root);
-    let opt_mv = rt.children.iter().map(|e| {
-        let (k, child) = e;
+/// The search tree's storage on its own, so that `benches/tree.rs` can time the shipped `NodeList`
+/// against the storage it replaced. Not part of the API.
+#[doc(hidden)]
+pub struct NodeStore<G: Game>(NodeList<G>);
+
+impl<G: Game> Default for NodeStore<G> {
+    fn default() -> Self {
+        NodeStore(NodeList::new(None, false))
+    }
+}
 
-        //let c = nodes.get(child);
+impl<G: Game> NodeStore<G> {
+    pub fn add_root(&self, game: G) -> NodeID {
+        self.0.add(MoveTreeNode::new_root(game))
+    }
 
-        (child.simulations, k)
+    /// Adds a node for `game` below `parent`, reached by `mv`, which need not be a legal move.
+    pub fn add_child(&self, parent: NodeID, mv: G::Move, game: G) -> NodeID {
+        let id = self.0.add_child(game, parent);
+        self.0.get(parent).children.insert(mv, Child::new(id, None));
+        id
+    }
+
+    pub fn children(&self, node_id: NodeID) -> Vec<NodeID> {
+        self.0.get(node_id).children.values().map(|child| child.node).collect()
+    }
+
+    /// Frees a node and everything below it.
+    pub fn free(&self, node_id: NodeID) {
+        self.0.drop_node(node_id, node_id);
+    }
+}
+
+/// The move with the most simulations, always preferring a proven win and avoiding a proven loss.
+fn best_move<M: Hash + Eq + Clone>(children: &ChildMap<M>) -> Option<M> {
+    let opt_mv = children.iter().map(|e| {
+        let (k, child) = e;
+        let rank = match child.proof {
+            Some(Proof::Win) => 2,
+            Some(Proof::Loss) => 0,
+            _ => 1,
+        };
+
+        ((rank, child.simulations), k)
     }).max_by(|x, y| x.0.cmp(&y.0));
 
     opt_mv.map(|i| i.1.clone())
 }
 
+fn best_move_confidence<M: Hash + Eq + Clone>(children: &ChildMap<M>) -> (Option<M>, f64) {
+    let mv = best_move(children);
+
+    let confidence = mv.clone()
+        .and_then(|m| {
+            children.get(&m)
+        })
+        .map(|c| {
+            match c.proof {
+                Some(proof) => proof.score(),
+                None => c.wins / c.games as f64,
+            }
+        })
+        .unwrap_or(0.0);
+
+    (mv, confidence)
+}
+
+fn search_done<G: Game + 'static>(nodes: &NodeList<G>, root: NodeID, searcher: &Searcher<G>, search: &Search, num_sims: u64) -> Response<G> {
+    let (best_move, confidence) = best_move_confidence(&searcher.root_children(nodes, root));
+
+    Response::Done {
+        best_move, confidence,
+        sims: num_sims - search.start_sims,
+        nodes: searcher.added(nodes) - search.start_nodes,
+        depth: search.depth,
+        time_elapsed: search.start_time.elapsed(),
+    }
+}
+
+fn solution_done<G: Game>(solution: &Solution<G::Move>, search: &Search) -> Response<G> {
+    Response::Done {
+        best_move: solution.best_move.clone(),
+        confidence: solution.value.score(),
+        sims: 0,
+        nodes: 0,
+        depth: 0,
+        time_elapsed: search.start_time.elapsed(),
+    }
+}
+
+/// Runs the iterations of the search.
+enum Searcher<G: Game> {
+    Leaf(SimThreadPool<G>),
+    Tree(TreeWorkers<G>),
+    Root(RootWorkers<G>),
+}
+
+impl<G> Searcher<G> where G: Game + 'static {
+    /// Runs one batch of iterations from `root`, returning the number of simulations run and the
+    /// depth of the deepest leaf.
+    fn search(&self, nodes: &NodeList<G>, root: NodeID, budget: SimBudget, exploration: f64, rand: &mut XorShiftRng) -> (u32, usize) {
+        match *self {
+            Searcher::Leaf(ref pool) => montecarlo(nodes, root, pool, budget, exploration, rand),
+            Searcher::Tree(ref workers) => workers.search(root, budget),
+            Searcher::Root(ref workers) => workers.search(root, budget),
+        }
+    }
+
+    /// The root's statistics for each move, combined over every tree being searched.
+    fn root_children(&self, nodes: &NodeList<G>, root: NodeID) -> ChildMap<G::Move> {
+        let mut children = nodes.get(root).children.clone();
+        if let Searcher::Root(ref workers) = *self {
+            workers.merge(&mut children);
+        }
+
+        children
+    }
+
+    /// The number of nodes in every tree being searched.
+    fn len(&self, nodes: &NodeList<G>) -> usize {
+        match *self {
+            Searcher::Root(ref workers) => nodes.len() + workers.len(),
+            _ => nodes.len(),
+        }
+    }
+
+    /// The number of nodes ever added to every tree being searched.
+    fn added(&self, nodes: &NodeList<G>) -> usize {
+        match *self {
+            Searcher::Root(ref workers) => nodes.added() + workers.added(),
+            _ => nodes.added(),
+        }
+    }
+
+    /// Makes room in every full tree being searched.
+    fn shrink(&self, nodes: &NodeList<G>, root: NodeID) {
+        if nodes.is_full() {
+            nodes.shrink(root);
+        }
+        if let Searcher::Root(ref workers) = *self {
+            workers.shrink();
+        }
+    }
+}
+
+/// A handle to an AI which continuously searches the game tree on a background thread.
+///
+/// An AI created with a seed is deterministic instead: it runs a fixed number of simulations per
+/// thread (on a single thread unless told otherwise) and only searches while handling a
+/// `Request::Go`, so the same settings, position and simulation, node or depth limit always
+/// produce the same tree and the same answer. With more than one thread, a seeded AI uses root
+/// parallelism in place of tree parallelism.
 pub struct Ai<G: Game> {
     to_thread: Sender<Request<G>>,
     from_thread: Receiver<Response<G>>,
+    thread: Option<JoinHandle<()>>,
 }
 
 impl<G> Ai<G> where G: Game + 'static {
-    pub fn new(game: G) -> Self {
+    pub fn new(game: G, seed: Option<u64>) -> Self {
+        Ai::with_settings(game, Settings { seed, ..Settings::default() })
+    }
+
+    pub fn with_settings(game: G, settings: Settings) -> Self {
         let (to_thread, from_outside) = channel();
         let (to_outside, from_thread) = channel();
 
-        thread::spawn(move || {
+        let thread = thread::spawn(move || {
             let start_time = Instant::now();
+            let seed = settings.seed;
+            let deterministic = seed.is_some();
 
             let mut num_sims: u64 = 0;
-            let mut nodes = NodeList::new();
+            let num_threads = settings.threads.unwrap_or_else(|| if deterministic { 1 } else { num_cpus::get() });
+            // threads sharing a tree depend on each other's timing, so a deterministic AI gives
+            // each of its threads a tree of its own instead
+            let parallelism = match settings.parallelism {
+                Parallelism::Tree if deterministic && num_threads > 1 => Parallelism::Root,
+                parallelism => parallelism,
+            };
+            let trees = if parallelism == Parallelism::Root { num_threads } else { 1 };
+            let max_nodes = match (settings.max_nodes, settings.max_memory.map(|bytes| bytes / trees / node_bytes::<G>())) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let nodes = Arc::new(NodeList::new(max_nodes, settings.transpositions));
             let mut root = nodes.add(MoveTreeNode::new_root(game));
-            let thread_pool = SimThreadPool::new();
+            let mut searcher = match parallelism {
+                Parallelism::Leaf => Searcher::Leaf(SimThreadPool::new(num_threads, seed, settings.rollout)),
+                Parallelism::Tree => Searcher::Tree(TreeWorkers::new(nodes.clone(), num_threads, seed, settings.rollout, settings.exploration)),
+                Parallelism::Root => Searcher::Root(RootWorkers::new(nodes.clone(), root, num_threads, seed, settings.rollout, settings.exploration)),
+            };
+            let mut rand = new_rng(seed, 0);
+            let mut search: Option<Search> = None;
+            let mut paused = false;
+            let mut ponder = if deterministic { Ponder::Off } else { Ponder::On };
+            let mut ponder_search: Option<Search> = None;
+            let mut tree_reuse = None;
+            // `None` until the solver has been tried on the root, then its solution if it found one
+            let mut endgame: Option<Option<Solution<G::Move>>> = None;
 
             loop {
-                //println!("#nodes: {}", nodes.len());
-                while let Ok(msg) = from_outside.try_recv() {
+                // there is nothing left to ponder in a solved position
+                let solved = endgame.as_ref().map(|solution| solution.is_some()).unwrap_or(false);
+                let pondering = is_pondering(&ponder, &ponder_search) && !solved;
+                let mut next_msg = if paused || (search.is_none() && !pondering) {
+                    // wait for something to do instead of searching in the background
+                    match from_outside.recv() {
+                        Ok(msg) => Some(msg),
+                        Err(_) => return,
+                    }
+                } else {
+                    from_outside.try_recv().ok()
+                };
+
+                while let Some(msg) = next_msg {
                     match msg {
                         Request::Info => {
-                            let mv = best_move(&nodes, root);
-                            let rt = nodes.get(root);
-
-                            let confidence = mv.clone()
-                                .and_then(|m| {
-                                    rt.children.get(&m)
-                                })
-                                .map(|c| {
-                                    c.wins as f64 / c.games as f64
-                                })
-                                .unwrap_or(0.0);
+                            let (mv, confidence, proof) = match endgame {
+                                Some(Some(ref solution)) => (solution.best_move.clone(), solution.value.score(), Some(solution.value)),
+                                _ => {
+                                    let children = searcher.root_children(&nodes, root);
+                                    let (mv, confidence) = best_move_confidence(&children);
+                                    (mv, confidence, proven(&children))
+                                },
+                            };
 
                             let stats = Response::Info {
                                 best_move: mv,
-                                confidence: confidence,
+                                confidence,
+                                proof,
                                 total_sims: num_sims,
-                                time_elapsed: start_time.elapsed()
+                                time_elapsed: start_time.elapsed(),
+                                tree_reuse,
+                                nodes: searcher.len(&nodes),
+                                memory: searcher.len(&nodes) * node_bytes::<G>(),
                             };
 
                             to_outside.send(stats).expect("Send failed (Info)");
                         },
 
                         Request::MakeMove(mv) => {
-                            root = {
+                            search = None;
+                            endgame = None;
+                            let nodes_before = nodes.subtree_size(root);
+                            let (new_root, existed) = nodes.advance(root, &mv);
+                            root = new_root;
+                            if let Searcher::Root(ref mut workers) = searcher {
+                                workers.make_move(&mv);
+                            }
 
-                                let new_root_id = {
-                                    let rt = nodes.take(root);
-                                    let id = rt.children.get(&mv)
-                                        .map(|v| v.node)
-                                        .unwrap_or_else(|| {
-                                            nodes.add(MoveTreeNode::new_root(*rt.game.make_move(&mv).expect("AI was asked to make an invalid move")))
-                                        });
-                                    nodes.restore(root, rt);
-                                    id
-                                };
+                            tree_reuse = Some(TreeReuse {
+                                nodes_before,
+                                nodes_kept: if existed { nodes.subtree_size(root) } else { 0 },
+                                // the node's own count, since its entry in the old root stops being
+                                // updated while it is the root
+                                sims_kept: nodes.get(root).games as u64,
+                            });
 
-                                let mut new_rt = {
-                                    nodes.take(new_root_id)
-                                };
+                            if let Ponder::Limited(ref limits) = ponder {
+                                ponder_search = Some(Search::new(limits.clone(), num_sims, searcher.added(&nodes)));
+                            }
 
-                                nodes.drop_node(root, new_root_id);
-                                new_rt.parent = None;
+                            to_outside.send(Response::Ok).expect("Send failed (Ok)");
+                        },
 
-                                nodes.restore(new_root_id, new_rt);
+                        Request::Undo => {
+                            let nodes_before = nodes.subtree_size(root);
+                            if let Some(prev_root) = nodes.retreat(root) {
+                                search = None;
+                                endgame = None;
+                                root = prev_root;
+                                if let Searcher::Root(ref mut workers) = searcher {
+                                    workers.undo();
+                                }
 
-                                new_root_id
-                            };
+                                tree_reuse = Some(TreeReuse {
+                                    nodes_before,
+                                    nodes_kept: nodes.subtree_size(root),
+                                    sims_kept: nodes.get(root).games as u64,
+                                });
+
+                                if let Ponder::Limited(ref limits) = ponder {
+                                    ponder_search = Some(Search::new(limits.clone(), num_sims, searcher.added(&nodes)));
+                                }
+                            }
+
+                            to_outside.send(Response::Ok).expect("Send failed (Ok)");
+                        },
+
+                        Request::SetPosition(game) => {
+                            search = None;
+                            endgame = None;
+                            let nodes_before = nodes.subtree_size(root);
+
+                            if let Searcher::Root(ref mut workers) = searcher {
+                                workers.set_position(&game);
+                            }
+                            nodes.clear();
+                            root = nodes.add(MoveTreeNode::new_root(game));
+
+                            tree_reuse = Some(TreeReuse {
+                                nodes_before,
+                                nodes_kept: 0,
+                                sims_kept: 0,
+                            });
+
+                            if let Ponder::Limited(ref limits) = ponder {
+                                ponder_search = Some(Search::new(limits.clone(), num_sims, searcher.added(&nodes)));
+                            }
 
                             to_outside.send(Response::Ok).expect("Send failed (Ok)");
-                        }
+                        },
+
+                        Request::Go { limits } => {
+                            search = Some(Search::new(limits, num_sims, searcher.added(&nodes)));
+                            // the pondering would otherwise pick up again after the search, with
+                            // the search's simulations counted against its limits
+                            ponder_search = None;
+
+                            if nodes.get(root).game.available_moves().is_empty() {
+                                // there is nothing to search for
+                                let s = search.take().unwrap();
+                                to_outside.send(search_done(&nodes, root, &searcher, &s, num_sims)).expect("Send failed (Done)");
+                            }
+                        },
+
+                        Request::Stop => {
+                            if let Some(mut s) = search.take() {
+                                // a search stopped before its first iteration still needs a move
+                                if searcher.root_children(&nodes, root).is_empty() {
+                                    let (sims, depth) = searcher.search(&nodes, root, SimBudget::Count(1), settings.exploration, &mut rand);
+                                    num_sims += sims as u64;
+                                    s.depth = s.depth.max(depth);
+                                }
+
+                                to_outside.send(search_done(&nodes, root, &searcher, &s, num_sims)).expect("Send failed (Done)");
+                            }
+                        },
+
+                        Request::Pause => paused = true,
+                        Request::Resume => paused = false,
+
+                        Request::SetPonder(p) => {
+                            ponder_search = match p {
+                                Ponder::Limited(ref limits) => Some(Search::new(limits.clone(), num_sims, searcher.added(&nodes))),
+                                _ => None,
+                            };
+                            ponder = p;
+                        },
+
+                        Request::Quit => return,
                     }
+
+                    next_msg = from_outside.try_recv().ok();
                 };
 
-                num_sims += montecarlo(&mut nodes, root, &thread_pool) as u64;
+                let pondering = is_pondering(&ponder, &ponder_search);
+                if paused || (search.is_none() && !pondering) {
+                    continue;
+                }
+
+                // a position with few enough moves is solved exactly, if it can be, instead of searched
+                if endgame.is_none() {
+                    let game = nodes.get(root).game.clone();
+                    let near_end = game.moves_left()
+                        .map(|left| left > 0 && left <= settings.endgame_moves)
+                        .unwrap_or(false);
+
+                    endgame = Some(if near_end {
+                        solve(&game, Some(ENDGAME_NODES))
+                    } else {
+                        None
+                    });
+                }
+                if let Some(Some(ref solution)) = endgame {
+                    if let Some(s) = search.take() {
+                        to_outside.send(solution_done(solution, &s)).expect("Send failed (Done)");
+                    }
+                    continue;
+                }
+
+                let budget = budget(search.as_ref().or(ponder_search.as_ref()), num_sims, deterministic);
+                let (sims, depth) = searcher.search(&nodes, root, budget, settings.exploration, &mut rand);
+                num_sims += sims as u64;
+
+                if settings.full_tree == FullTree::Prune {
+                    searcher.shrink(&nodes, root);
+                }
+
+                if let Some(mut s) = search.take() {
+                    s.depth = s.depth.max(depth);
+
+                    if s.finished(num_sims, searcher.added(&nodes)) {
+                        to_outside.send(search_done(&nodes, root, &searcher, &s, num_sims)).expect("Send failed (Done)");
+                    } else {
+                        search = Some(s);
+                    }
+                } else if let Some(mut s) = ponder_search.take() {
+                    s.depth = s.depth.max(depth);
+
+                    if !s.finished(num_sims, searcher.added(&nodes)) {
+                        ponder_search = Some(s);
+                    }
+                }
             }
         });
 
         Ai {
             to_thread, from_thread,
+            thread: Some(thread),
         }
     }
 
@@ -189,8 +1131,26 @@ impl<G> Ai<G> where G: Game + 'static {
         }
     }
 
+    /// Waits until the AI thread sends its next response.
+    pub fn recv_blocking(&self) -> Response<G> {
+        self.from_thread.recv().expect("AI thread disconnected")
+    }
+
 
     pub fn make_move(&self, mv: G::Move) {
         self.to_thread.send(Request::MakeMove(mv)).unwrap();
     }
-}
\ No newline at end of file
+}
+
+impl<G: Game> Drop for Ai<G> {
+    fn drop(&mut self) {
+        // the thread may already have stopped if it was sent Quit
+        let _ = self.to_thread.send(Request::Quit);
+
+        if let Some(thread) = self.thread.take() {
+            // the thread has already reported its own panic, and panicking again here would abort if
+            // this is dropped while unwinding
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,5 +1,27 @@
 use std::hash::Hash;
 
+use rand::Rng;
+
+/// How a finished game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameResult<P> {
+	Win(P),
+	Draw,
+}
+
+impl<P: PartialEq> GameResult<P> {
+	/// Scores the result from `player`'s point of view: 1 for a win, 0.5 for a draw and 0 for a
+	/// loss.
+	pub fn score(&self, player: &P) -> f64 {
+		match *self {
+			GameResult::Win(ref winner) if winner == player => 1.0,
+			GameResult::Win(_) => 0.0,
+			GameResult::Draw => 0.5,
+		}
+	}
+}
+
+/// A two player game that the AI can search.
 pub trait Game: Clone + Send {
 	type Move: Hash + Eq + Clone + Send;
 	type Player: Hash + Eq + Clone + Send;
@@ -10,5 +32,30 @@ pub trait Game: Clone + Send {
 	fn get_cur_player(&self) -> Self::Player;
 	fn get_winner(&self) -> Option<Self::Player>;
 
+	/// Returns the result once the game is over. By default a game without a winner is a draw
+	/// once there are no moves left.
+	fn get_result(&self) -> Option<GameResult<Self::Player>> {
+		match self.get_winner() {
+			Some(winner) => Some(GameResult::Win(winner)),
+			None if self.available_moves().is_empty() => Some(GameResult::Draw),
+			None => None,
+		}
+	}
+
+	/// An upper bound on the number of moves left before the game ends, such as the empty cells that
+	/// can still be played, which is how the endgame solver knows when to start. Games that don't
+	/// provide one are never solved.
+	fn moves_left(&self) -> Option<usize> { None }
+
+	/// A hash of the position, used to find the same position reached by different moves. Equal
+	/// positions must give equal keys. Games that don't provide one are never merged.
+	fn hash_key(&self) -> Option<u64> { None }
+
+	/// Picks a uniformly random legal move, used to play out simulations. Games can override this
+	/// to avoid building the whole move list.
+	fn random_move<R: Rng>(&self, rand: &mut R) -> Option<Self::Move> {
+		rand.choose(&self.available_moves()).cloned()
+	}
+
 	fn to_str(&self) -> String { String::new() }
-}
\ No newline at end of file
+}
@@ -1,8 +1,19 @@
-pub mod game;
-pub mod ai;
+mod game;
+#[allow(clippy::module_inception)]
+mod ai;
 mod tree;
 mod montecarlo;
 mod sim_thread_pool;
+mod tree_parallel;
+mod root_parallel;
+mod solver;
+mod random;
 
-pub use self::ai::Ai;
-pub use self::game::Game;
\ No newline at end of file
+pub use self::ai::{ Ai, FullTree, Limits, Parallelism, Ponder, Proof, Request, Response, Rollout, Settings, TreeReuse };
+pub use self::game::{ Game, GameResult };
+pub use self::solver::{ solve, Solution };
+#[doc(hidden)]
+pub use self::ai::NodeStore;
+
+#[cfg(test)]
+mod tests;
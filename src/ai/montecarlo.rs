@@ -1,18 +1,15 @@
-use std::f64::INFINITY;
-
-use rand::{ thread_rng, Rng };
+use rand::{ Rng, XorShiftRng };
 
 use super::Game;
 use super::tree::*;
-use super::ai::{ NodeID, NodeList };
-use ai::sim_thread_pool::SimThreadPool;
-
-const EXPLORATION_FACTOR: f64 = 1.4142135623730950488016887242097; // sqrt(2)
+use super::ai::{ NodeID, NodeList, Proof };
+use ai::sim_thread_pool::{ SimThreadPool, SimBudget };
 
 fn all_max<'a, K, I>(list: I) -> (f64, Vec<(&'a K, &'a Child)> )
     where I: Iterator<Item=(&'a K, &'a Child)>
 {
-    list.into_iter().fold((0.0, Vec::new()), |acc, entry| {
+    // starts below every value, since a proven loss has the lowest possible value
+    list.into_iter().fold((f64::NEG_INFINITY, Vec::new()), |acc, entry| {
         let (max, mut items) = acc;
         let uct = entry.1.uct;
 
@@ -27,86 +24,129 @@ fn all_max<'a, K, I>(list: I) -> (f64, Vec<(&'a K, &'a Child)> )
     })
 }
 
-pub(in super) fn montecarlo<G: Game + 'static>(nodes: &mut NodeList<G>, root: NodeID, thread_pool: &SimThreadPool<G>) -> u32 {
-    let mut rand = thread_rng();
+/// Runs one iteration of the search, returning the number of simulations run and the depth of the
+/// node they were run from. `exploration` is the UCT exploration constant.
+///
+/// A move whose result is proven isn't searched any further; choosing it counts as a single game
+/// with that result, and whenever a move's result settles a position, the position's own move is
+/// proven in turn on the way back up.
+pub(in super) fn montecarlo<G: Game + 'static>(nodes: &NodeList<G>, root: NodeID, thread_pool: &SimThreadPool<G>, budget: SimBudget, exploration: f64, rand: &mut XorShiftRng) -> (u32, usize) {
 
     // Select
     let mut cur_node_id = root;
     let mut sim_this = false;
+    let mut proven_leaf = None;
     let mut path = Vec::new();
     while !sim_this {
-        let mut node = nodes.take(cur_node_id);
-        let last_node_id = cur_node_id;
-
-        if node.children.len() > 0 {
-            let (max_val, max_list) = all_max(node.children.iter());
-            let (mv, child) = *rand.choose(&max_list).unwrap();
-            path.push((cur_node_id, mv.clone()));
-
-            cur_node_id = child.node;
-
-
-            if max_val == INFINITY {
-                // simulate this node
-                sim_this = true;
+        let game = {
+            let node = nodes.get(cur_node_id);
+
+            if !node.children.is_empty() {
+                let (max_val, max_list) = all_max(node.children.iter());
+                let (mv, child) = *rand.choose(&max_list).unwrap();
+                path.push((cur_node_id, mv.clone()));
+
+                cur_node_id = child.node;
+
+                if let Some(proof) = child.proof {
+                    // the result is known, so there is nothing to simulate
+                    proven_leaf = Some((node.player.clone(), proof));
+                    sim_this = true;
+                } else if max_val == f64::INFINITY {
+                    // simulate this node
+                    sim_this = true;
+                }
+                // otherwise, select again from this node
+                continue;
             }
-            // otherwise, select again from this node
 
-        } else {
-            // Expand
-            for mv in node.game.available_moves() {
-                let new_game = *node.game.make_move(&mv).unwrap();
-                let new_node = nodes.add(MoveTreeNode::new(new_game, cur_node_id));
-                node.children.insert(mv, Child {
-                    games: 0,
-                    wins: 0,
-                    uct: INFINITY,
-                    simulations: 0,
-                    node: new_node,
-                });
-            }
-
-            if node.children.len() == 0 {
-                sim_this = true;
-            }
+            node.game.clone()
+        };
 
-            // select a child from the current node
+        // Expand, unless the tree is full. The children are added without holding the node,
+        // whose shard they may share.
+        let player = game.get_cur_player();
+        let children: Vec<(G::Move, Child)> = if nodes.is_full() {
+            Vec::new()
+        } else {
+            game.available_moves().into_iter()
+                .map(|mv| {
+                    let new_game = *game.make_move(&mv).unwrap();
+                    let proof = Proof::of_game(&new_game, &player);
+                    (mv, Child::new(nodes.add_child(new_game, cur_node_id), proof))
+                })
+                .collect()
+        };
+
+        if children.is_empty() {
+            sim_this = true;
         }
 
-        nodes.restore(last_node_id, node);
+        let mut node = nodes.get(cur_node_id);
+        node.children.extend(children);
+
+        // select a child from the current node
     }
 
-    // Simulate
-    let (num_sims, results) = {
-        let cur_node = nodes.get_mut(cur_node_id);
+    // Simulate, unless the result is already known
+    let results = match proven_leaf {
+        Some(_) => None,
+        None => {
+            let cur_node = nodes.get(cur_node_id);
 
-        thread_pool.simulate(cur_node.game.clone(), 25)
+            Some(thread_pool.simulate(cur_node.game.clone(), budget))
+        },
     };
+    let num_sims = results.as_ref().map(|results| results.sims).unwrap_or(1);
+
+    let depth = path.len();
 
-    // Backprop
-    for (node_id, mv) in path {
-        let mut cur_node = nodes.get_mut(node_id);
+    // Backprop, only along the path actually taken, since a transposed node has other parents.
+    // `proof` is the result just proven for the player to move below, if there is one.
+    let mut proof = None;
+    let mut deepest = true;
+    for (node_id, mv) in path.into_iter().rev() {
+        let mut cur_node = nodes.get(node_id);
         let player = &cur_node.player.clone();
 
         cur_node.games += num_sims;
 
         {
-            let mut child = cur_node.children.get_mut(&mv).unwrap();
+            let child = cur_node.children.get_mut(&mv).unwrap();
+            if let Some((ref below, proof)) = proof {
+                child.proof = Some(if below == player { proof } else { proof.flip() });
+            }
+
             child.games += num_sims;
             child.simulations += 1;
-            if let Some(&wins) = results.get(&player) {
-                child.wins += wins;
-            }
+            child.wins += match (&results, &proven_leaf) {
+                (Some(results), _) => results.score(player),
+                (None, Some((prover, proof))) => proof.score_for(prover, player),
+                (None, None) => unreachable!("nothing was simulated"),
+            };
         }
 
+        // the deepest node may have just been expanded into moves that end the game, otherwise a
+        // node can only be proven once the node below it is
+        proof = if proof.is_some() || deepest {
+            proven(&cur_node.children).map(|proof| (player.clone(), proof))
+        } else {
+            None
+        };
+        deepest = false;
+
         let total_games = cur_node.games;
         for (_, child) in cur_node.children.iter_mut() {
-            if child.games != 0 {
-                child.uct = (child.wins as f64 / child.games as f64) + EXPLORATION_FACTOR * ((total_games as f64).ln() / (child.games as f64)).sqrt();
+            match child.proof {
+                Some(Proof::Win) => child.uct = f64::INFINITY,
+                Some(Proof::Loss) => child.uct = f64::NEG_INFINITY,
+                _ if child.games != 0 => {
+                    child.uct = (child.wins / child.games as f64) + exploration * ((total_games as f64).ln() / (child.games as f64)).sqrt();
+                },
+                _ => {},
             }
         }
     }
 
-    num_sims
-
-}
\ No newline at end of file
+    (num_sims, depth)
+}
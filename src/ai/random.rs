@@ -0,0 +1,26 @@
+use rand::{ weak_rng, SeedableRng, XorShiftRng };
+
+/// SplitMix64, used to spread a single seed over the state of a `XorShiftRng`.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Creates a random number generator. With a seed, each `stream` gives a different but
+/// reproducible sequence; without one, the generator is seeded by the OS.
+pub(in super) fn new_rng(seed: Option<u64>, stream: u64) -> XorShiftRng {
+    match seed {
+        Some(seed) => {
+            let mut state = seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let (a, b) = (splitmix64(&mut state), splitmix64(&mut state));
+
+            // an all-zero state would only ever produce zeroes
+            let words = [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1];
+            XorShiftRng::from_seed(words)
+        },
+        None => weak_rng(),
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use super::{ Game, Rollout };
+use super::tree::*;
+use super::ai::{ NodeID, NodeList };
+use super::sim_thread_pool::SimBudget;
+use super::tree_parallel::TreeWorkers;
+
+/// Searches with every worker growing its own tree from the same root (root parallelisation).
+/// The first worker grows the AI's own tree and the others grow helper trees, which follow it
+/// through every change of position. Only the root's statistics are combined.
+pub(in super) struct RootWorkers<G: Game> {
+    /// The helper trees and their roots.
+    helpers: Vec<(Arc<NodeList<G>>, NodeID)>,
+    workers: TreeWorkers<G>,
+}
+
+impl<G> RootWorkers<G> where G: Game + 'static {
+    /// Starts `num_threads` workers, the first on `nodes`, whose root is `root`. The helper trees
+    /// have the same settings as `nodes`.
+    pub fn new(nodes: Arc<NodeList<G>>, root: NodeID, num_threads: usize, seed: Option<u64>, rollout: Rollout, exploration: f64) -> Self {
+        let game = nodes.get(root).game.clone();
+        let helpers: Vec<(Arc<NodeList<G>>, NodeID)> = (1..num_threads)
+            .map(|_| {
+                let tree = Arc::new(nodes.new_like());
+                let root = tree.add(MoveTreeNode::new_root(game.clone()));
+                (tree, root)
+            })
+            .collect();
+
+        let trees = Some(nodes).into_iter()
+            .chain(helpers.iter().map(|(tree, _)| tree.clone()))
+            .collect();
+
+        RootWorkers {
+            helpers,
+            workers: TreeWorkers::with_trees(trees, seed, rollout, exploration),
+        }
+    }
+
+    /// Searches from `root` in the AI's tree and from the current root of every helper tree.
+    pub fn search(&self, root: NodeID, budget: SimBudget) -> (u32, usize) {
+        let roots: Vec<NodeID> = Some(root).into_iter()
+            .chain(self.helpers.iter().map(|&(_, root)| root))
+            .collect();
+
+        self.workers.search_from(&roots, budget)
+    }
+
+    /// The number of nodes in the helper trees.
+    pub fn len(&self) -> usize {
+        self.helpers.iter().map(|(tree, _)| tree.len()).sum()
+    }
+
+    /// The number of nodes ever added to the helper trees.
+    pub fn added(&self) -> usize {
+        self.helpers.iter().map(|(tree, _)| tree.added()).sum()
+    }
+
+    /// Makes room in every full helper tree.
+    pub fn shrink(&self) {
+        for &(ref tree, root) in &self.helpers {
+            if tree.is_full() {
+                tree.shrink(root);
+            }
+        }
+    }
+
+    /// Adds the helper trees' statistics for each move from the root to `children`, along with any
+    /// result a helper has proven.
+    pub fn merge(&self, children: &mut ChildMap<G::Move>) {
+        for &(ref tree, root) in &self.helpers {
+            for (mv, helper) in &tree.get(root).children {
+                match children.get_mut(mv) {
+                    Some(child) => {
+                        child.games += helper.games;
+                        child.wins += helper.wins;
+                        child.simulations += helper.simulations;
+                        child.proof = child.proof.or(helper.proof);
+                    },
+                    // the entry's node is in the helper's tree, but only its statistics are used
+                    None => { children.insert(mv.clone(), helper.clone()); },
+                }
+            }
+        }
+    }
+
+    pub fn make_move(&mut self, mv: &G::Move) {
+        for &mut (ref tree, ref mut root) in &mut self.helpers {
+            *root = tree.advance(*root, mv).0;
+        }
+    }
+
+    pub fn undo(&mut self) {
+        for &mut (ref tree, ref mut root) in &mut self.helpers {
+            if let Some(prev_root) = tree.retreat(*root) {
+                *root = prev_root;
+            }
+        }
+    }
+
+    pub fn set_position(&mut self, game: &G) {
+        for &mut (ref tree, ref mut root) in &mut self.helpers {
+            tree.clear();
+            *root = tree.add(MoveTreeNode::new_root(game.clone()));
+        }
+    }
+}
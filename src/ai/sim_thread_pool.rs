@@ -1,86 +1,168 @@
 use std::collections::HashMap;
-use std::thread;
+use std::hash::Hash;
+use std::thread::{ self, JoinHandle };
 use std::sync::mpsc::{ channel, Sender, Receiver };
 use std::time::{ Instant, Duration };
 
-use rand::{ thread_rng, Rng };
-use num_cpus;
+use rand::Rng;
 
-use super::Game;
+use super::{ Game, GameResult, Rollout };
+use super::random::new_rng;
+
+/// The outcome of a batch of simulations.
+pub(in super) struct SimResults<P> {
+    pub sims: u32,
+    pub wins: HashMap<P, u32>,
+    pub draws: u32,
+}
+
+impl<P> SimResults<P> where P: Hash + Eq + Clone {
+    fn new() -> Self {
+        SimResults {
+            sims: 0,
+            wins: HashMap::new(),
+            draws: 0,
+        }
+    }
+
+    /// Half a point for every draw and a point for every win.
+    pub fn score(&self, player: &P) -> f64 {
+        self.wins.get(player).cloned().unwrap_or(0) as f64 + self.draws as f64 / 2.0
+    }
+}
+
+/// How many simulations a call to `SimThreadPool::simulate` runs.
+#[derive(Clone, Copy, Debug)]
+pub(in super) enum SimBudget {
+    /// Simulate on every thread for this many milliseconds.
+    Time(u64),
+    /// Run exactly this many simulations, split between the threads.
+    Count(u32),
+}
+
+/// Picks the next move of a playout.
+fn rollout_move<G: Game, R: Rng>(game: &G, rollout: Rollout, rand: &mut R) -> Option<G::Move> {
+    if rollout == Rollout::WinFirst {
+        let player = Some(game.get_cur_player());
+        let winning_move = game.available_moves().into_iter()
+            .find(|mv| game.make_move(mv).map(|g| g.get_winner() == player).unwrap_or(false));
+
+        if winning_move.is_some() {
+            return winning_move;
+        }
+    }
+
+    game.random_move(rand)
+}
+
+/// Plays moves from `game` until it ends. A game that runs out of moves without a result counts
+/// as a draw.
+pub(in super) fn playout<G: Game, R: Rng>(game: &G, rollout: Rollout, rand: &mut R) -> GameResult<G::Player> {
+    let mut g = game.clone();
+
+    while g.get_result().is_none() {
+        if let Some(mv) = rollout_move(&g, rollout, rand) {
+            g.make_move_mut(&mv);
+        } else {
+            // no possible moves
+            break;
+        }
+    }
+
+    g.get_result().unwrap_or(GameResult::Draw)
+}
 
 pub(in super) struct SimThreadPool<G: Game> {
-    senders: Vec<Sender<(G, u64)>>,
-    receivers: Vec<Receiver<(u32, HashMap<G::Player, u32>)>>,
+    senders: Vec<Sender<(G, SimBudget)>>,
+    receivers: Vec<Receiver<SimResults<G::Player>>>,
+    threads: Vec<JoinHandle<()>>,
 }
 
 impl<G> SimThreadPool<G> where G: Game + 'static {
-    pub fn new() -> Self {
-        let (senders, receivers) = (0..num_cpus::get())
-            .map(|_| {
-                let (to_thread, from_outside) = channel::<(G, u64)>();
-                let (to_outside, from_thread) = channel::<(u32, HashMap<G::Player, u32>)>();
-
-                thread::spawn(move || {
-                    let mut rand = thread_rng();
-
-                    loop {
-                        let (game, time) = from_outside.recv().unwrap();
-                        let start = Instant::now();
-                        let time_limit = Duration::from_millis(time);
-
-                        let mut num_sims = 0;
-                        let mut results: HashMap<G::Player, u32> = HashMap::new();
-                        while start.elapsed() < time_limit {
-                            num_sims += 1;
-
-                            let mut g = game.clone();
-
-                            while g.get_winner().is_none() {
-                                let moves = { &g.available_moves() };
-                                if let Some(mv) = rand.choose(moves) {
-                                    g.make_move_mut(mv);
-                                } else {
-                                    // no possible moves
-                                    break;
-                                }
-                            }
-
-                            if let Some(winner) = g.get_winner() {
-                                let new_val = { results.get(&winner) }.unwrap_or(&0) + 1;
-                                results.insert(winner, new_val);
-                            }
+    /// Starts `num_threads` simulation threads. With a seed, thread `i` uses random stream `i + 1`.
+    pub fn new(num_threads: usize, seed: Option<u64>, rollout: Rollout) -> Self {
+        let mut pool = SimThreadPool {
+            senders: Vec::new(),
+            receivers: Vec::new(),
+            threads: Vec::new(),
+        };
+
+        for i in 0..num_threads {
+            let (to_thread, from_outside) = channel::<(G, SimBudget)>();
+            let (to_outside, from_thread) = channel::<SimResults<G::Player>>();
+
+            let thread = thread::spawn(move || {
+                let mut rand = new_rng(seed, i as u64 + 1);
+
+                loop {
+                    let (game, budget) = match from_outside.recv() {
+                        Ok(msg) => msg,
+                        Err(_) => return, // the pool was dropped
+                    };
+                    let start = Instant::now();
+
+                    let mut results = SimResults::new();
+                    while match budget {
+                        SimBudget::Time(time) => start.elapsed() < Duration::from_millis(time),
+                        SimBudget::Count(count) => results.sims < count,
+                    } {
+                        results.sims += 1;
+
+                        match playout(&game, rollout, &mut rand) {
+                            GameResult::Win(winner) => *results.wins.entry(winner).or_insert(0) += 1,
+                            GameResult::Draw => results.draws += 1,
                         }
-
-                        to_outside.send((num_sims, results)).unwrap();
                     }
-                });
 
-                (to_thread, from_thread)
-            })
-            .unzip();
+                    to_outside.send(results).unwrap();
+                }
+            });
 
-        SimThreadPool {
-            senders, receivers
+            pool.senders.push(to_thread);
+            pool.receivers.push(from_thread);
+            pool.threads.push(thread);
         }
+
+        pool
     }
 
-    pub fn simulate(&self, game: G, time_limit: u64) -> (u32, HashMap<G::Player, u32>) {
-        for thread in self.senders.iter() {
-            thread.send((game.clone(), time_limit)).unwrap();
+    pub fn simulate(&self, game: G, budget: SimBudget) -> SimResults<G::Player> {
+        let num_threads = self.senders.len() as u32;
+        for (i, thread) in self.senders.iter().enumerate() {
+            let thread_budget = match budget {
+                SimBudget::Time(time) => SimBudget::Time(time),
+                SimBudget::Count(count) => {
+                    let extra = if (i as u32) < count % num_threads { 1 } else { 0 };
+                    SimBudget::Count(count / num_threads + extra)
+                },
+            };
+
+            thread.send((game.clone(), thread_budget)).unwrap();
         }
 
-        let mut results: HashMap<G::Player, u32> = HashMap::new();
-        let mut num_sims = 0;
+        let mut results = SimResults::new();
         for thread in self.receivers.iter() {
-            let (thread_num_sims, thread_results) = thread.recv().unwrap();
+            let thread_results = thread.recv().unwrap();
 
-            num_sims += thread_num_sims;
-            for (player, thread_wins) in thread_results.iter() {
-                let new_val = { results.get(player) }.unwrap_or(&0) + *thread_wins;
-                results.insert(player.clone(), new_val);
+            results.sims += thread_results.sims;
+            results.draws += thread_results.draws;
+            for (player, thread_wins) in thread_results.wins {
+                *results.wins.entry(player).or_insert(0) += thread_wins;
             }
         }
 
-        (num_sims, results)
+        results
     }
-}
\ No newline at end of file
+}
+
+impl<G: Game> Drop for SimThreadPool<G> {
+    fn drop(&mut self) {
+        // closing the channels makes each thread return once it finishes its current batch
+        self.senders.clear();
+
+        for thread in self.threads.drain(..) {
+            // a thread that panicked has already said so; see `Ai`'s drop
+            let _ = thread.join();
+        }
+    }
+}
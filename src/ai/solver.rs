@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use super::Game;
+use super::ai::Proof;
+
+/// The value of a position for the player to move: 1 for a win, 0 for a draw and -1 for a loss.
+type Value = i8;
+
+fn from_proof(proof: Proof) -> Value {
+    match proof {
+        Proof::Win => 1,
+        Proof::Draw => 0,
+        Proof::Loss => -1,
+    }
+}
+
+fn to_proof(value: Value) -> Proof {
+    match value {
+        1 => Proof::Win,
+        0 => Proof::Draw,
+        _ => Proof::Loss,
+    }
+}
+
+/// The exact result of a position, found by `solve`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution<M> {
+    /// A move that keeps the best result, or `None` if the game is already over.
+    pub best_move: Option<M>,
+    /// The result for the player to move with best play from both sides.
+    pub value: Proof,
+    /// The number of positions searched to find it.
+    pub nodes: u64,
+}
+
+struct Solver {
+    nodes: u64,
+    max_nodes: Option<u64>,
+    /// The bounds already known on the value of each position, by `Game::hash_key`.
+    table: HashMap<u64, (Value, Value)>,
+}
+
+impl Solver {
+    /// The value of `game` between `alpha` and `beta`, or a bound on it outside them. Returns
+    /// `None` once the node limit is reached.
+    fn search<G: Game>(&mut self, game: &G, mut alpha: Value, mut beta: Value) -> Option<Value> {
+        let player = game.get_cur_player();
+        if let Some(proof) = Proof::of_game(game, &player) {
+            return Some(from_proof(proof));
+        }
+
+        self.nodes += 1;
+        if self.max_nodes.map(|max| self.nodes > max).unwrap_or(false) {
+            return None;
+        }
+
+        let key = game.hash_key();
+        let (lower, upper) = key.and_then(|key| self.table.get(&key).cloned()).unwrap_or((-1, 1));
+        if lower >= beta || lower == upper {
+            return Some(lower);
+        }
+        if upper <= alpha {
+            return Some(upper);
+        }
+        alpha = alpha.max(lower);
+        beta = beta.min(upper);
+        let window = (alpha, beta);
+
+        // moves that win straight away are tried first, since they end the search here
+        let mut children: Vec<G> = game.available_moves().iter()
+            .map(|mv| *game.make_move(mv).unwrap())
+            .collect();
+        children.sort_by_key(|child| child.get_winner() != Some(player.clone()));
+
+        let mut best = -1;
+        for child in &children {
+            let value = if child.get_cur_player() == player {
+                self.search(child, alpha, beta)?
+            } else {
+                -self.search(child, -beta, -alpha)?
+            };
+
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(key) = key {
+            // a value outside the window is only a bound
+            let bounds = if best <= window.0 {
+                (lower, best)
+            } else if best >= window.1 {
+                (best, upper)
+            } else {
+                (best, best)
+            };
+            self.table.insert(key, bounds);
+        }
+
+        Some(best)
+    }
+}
+
+/// Works out the result of `game` with best play by searching every line to the end with
+/// alpha-beta pruning, along with a move that achieves it. Gives up, returning `None`, after
+/// searching `max_nodes` positions.
+///
+/// Positions reached by different move orders are only searched once for games that provide
+/// `Game::hash_key`.
+pub fn solve<G: Game>(game: &G, max_nodes: Option<u64>) -> Option<Solution<G::Move>> {
+    let mut solver = Solver { nodes: 0, max_nodes, table: HashMap::new() };
+    let player = game.get_cur_player();
+
+    if let Some(value) = Proof::of_game(game, &player) {
+        return Some(Solution { best_move: None, value, nodes: 0 });
+    }
+
+    // the first move to raise alpha is the best so far, since the others only give bounds
+    let mut best = None;
+    let mut alpha = -1;
+    for mv in game.available_moves() {
+        let child = *game.make_move(&mv).unwrap();
+        let value = if child.get_cur_player() == player {
+            solver.search(&child, alpha, 1)?
+        } else {
+            -solver.search(&child, -1, -alpha)?
+        };
+
+        if best.is_none() || value > alpha {
+            best = Some(mv);
+            alpha = alpha.max(value);
+        }
+        if alpha == 1 {
+            break;
+        }
+    }
+
+    Some(Solution {
+        best_move: best,
+        value: to_proof(alpha),
+        nodes: solver.nodes,
+    })
+}
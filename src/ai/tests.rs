@@ -0,0 +1,694 @@
+use std::collections::{ HashMap, HashSet };
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::{ Rng, SeedableRng, XorShiftRng };
+
+use game::{ Player, Rules, TicTacToe };
+use super::*;
+use super::ai::{ NodeID, NodeList, SHARDS };
+use super::montecarlo::montecarlo;
+use super::root_parallel::RootWorkers;
+use super::sim_thread_pool::{ SimBudget, SimThreadPool };
+use super::tree::{ Child, ChildMap, MoveTreeNode };
+
+/// O can win the game with (2, 2), among all the moves of a free move.
+const O_WINS_WITH_2_2: &str = "OOOXX1X2/OOOXX1X2/OO1XX4/9/9/9/9/9/9 OO7 - O";
+/// X has to play in the top row of board 3, which lets O play (2, 2) and win the game.
+const X_LOSES: &str = "OOOXX1X2/OOOXX1X2/OO1XX4/3XOXOXO/9/9/9/9/9 OO7 3 X";
+
+/// A position written in `TicTacToe::to_notation`'s notation, with the standard rules.
+fn position(notation: &str) -> TicTacToe {
+	TicTacToe::from_notation(notation, Rules::default()).unwrap()
+}
+
+fn sims(n: u64) -> Limits {
+	Limits { sims: Some(n), ..Limits::default() }
+}
+
+/// What a `Response::Done` reported.
+#[derive(Debug)]
+struct Done {
+	best_move: Option<(usize, usize)>,
+	confidence: f64,
+	sims: u64,
+	nodes: usize,
+	depth: usize,
+	time: Duration,
+}
+
+impl Done {
+	/// Everything but the time, which is the same for two searches that went the same way.
+	fn outcome(&self) -> (Option<(usize, usize)>, f64, u64, usize, usize) {
+		(self.best_move, self.confidence, self.sims, self.nodes, self.depth)
+	}
+}
+
+/// What a `Response::Info` reported.
+#[derive(Debug)]
+struct Info {
+	best_move: Option<(usize, usize)>,
+	proof: Option<Proof>,
+	total_sims: u64,
+	tree_reuse: Option<TreeReuse>,
+	nodes: usize,
+	memory: usize,
+}
+
+/// Starts a search with `limits` and waits for it to finish.
+fn go(ai: &Ai<TicTacToe>, limits: Limits) -> Done {
+	ai.send(Request::Go { limits });
+	match ai.recv_blocking() {
+		Response::Done { best_move, confidence, sims, nodes, depth, time_elapsed } =>
+			Done { best_move, confidence, sims, nodes, depth, time: time_elapsed },
+		_ => panic!("expected the search to finish"),
+	}
+}
+
+fn info(ai: &Ai<TicTacToe>) -> Info {
+	ai.send(Request::Info);
+	match ai.recv_blocking() {
+		Response::Info { best_move, proof, total_sims, tree_reuse, nodes, memory, .. } =>
+			Info { best_move, proof, total_sims, tree_reuse, nodes, memory },
+		_ => panic!("expected info"),
+	}
+}
+
+/// Searches `game` with an AI with `settings` until `limits` are reached, returning what the AI
+/// reported when the search finished and its info after it.
+fn search(game: TicTacToe, settings: Settings, limits: Limits) -> (Done, Info) {
+	let ai = Ai::with_settings(game, settings);
+	let done = go(&ai, limits);
+	(done, info(&ai))
+}
+
+/// Asks the AI for info until it has run at least `n` simulations in total, returning how many it
+/// had run.
+fn wait_for_sims(ai: &Ai<TicTacToe>, n: u64) -> u64 {
+	loop {
+		let total_sims = info(ai).total_sims;
+		if total_sims >= n {
+			return total_sims;
+		}
+	}
+}
+
+fn make_move(ai: &Ai<TicTacToe>, mv: (usize, usize)) {
+	ai.make_move(mv);
+	match ai.recv_blocking() {
+		Response::Ok => {},
+		_ => panic!("expected the move to be made"),
+	}
+}
+
+/// Plays `moves` moves from the start with a seeded AI searching `n` simulations a move, returning
+/// the moves it chose.
+fn seeded_game(settings: Settings, moves: usize, n: u64) -> Vec<(usize, usize)> {
+	let ai = Ai::with_settings(TicTacToe::new(), settings);
+	let mut played = Vec::new();
+
+	for _ in 0..moves {
+		let mv = go(&ai, sims(n)).best_move.expect("expected a move");
+		make_move(&ai, mv);
+		played.push(mv);
+	}
+	played
+}
+
+#[test]
+fn two_ais_with_the_same_seed_search_the_same_way() {
+	for &parallelism in &[Parallelism::Leaf, Parallelism::Tree, Parallelism::Root] {
+		let settings = Settings { seed: Some(7), threads: Some(1), parallelism, ..Settings::default() };
+
+		let first = search(TicTacToe::new(), settings.clone(), sims(2000)).0;
+		assert_eq!(search(TicTacToe::new(), settings, sims(2000)).0.outcome(), first.outcome(), "{:?}", parallelism);
+	}
+}
+
+#[test]
+fn two_ais_with_the_same_seed_play_the_same_game() {
+	let settings = Settings { seed: Some(3), threads: Some(1), ..Settings::default() };
+
+	let first = seeded_game(settings.clone(), 6, 500);
+	assert_eq!(seeded_game(settings, 6, 500), first);
+}
+
+#[test]
+fn seeded_searches_on_several_threads_are_reproducible() {
+	let settings = Settings { seed: Some(1), threads: Some(4), ..Settings::default() };
+
+	let first = search(TicTacToe::new(), settings.clone(), sims(4000)).0;
+	assert_eq!(search(TicTacToe::new(), settings, sims(4000)).0.outcome(), first.outcome());
+}
+
+#[test]
+fn settings_are_set_from_text() {
+	let mut settings = Settings::default();
+	settings.set("threads", "3").unwrap();
+	settings.set("rollout", "win-first").unwrap();
+	settings.set("memory", "2").unwrap();
+	settings.set("transpositions", "on").unwrap();
+
+	assert_eq!(settings, Settings {
+		threads: Some(3),
+		rollout: Rollout::WinFirst,
+		max_memory: Some(2 * 1024 * 1024),
+		transpositions: true,
+		..Settings::default()
+	});
+}
+
+#[test]
+fn bad_settings_say_what_was_expected() {
+	let mut settings = Settings::default();
+
+	assert_eq!(settings.set("threads", "0"), Err("expects a whole number above 0".to_owned()));
+	assert_eq!(settings.set("exploration", "-1"), Err("expects a number of at least 0".to_owned()));
+	assert_eq!(settings.set("parallelism", "forest"), Err("expects tree, root or leaf".to_owned()));
+	assert_eq!(settings.set("colour", "red"), Err("is not a setting".to_owned()));
+	assert_eq!(settings, Settings::default());
+}
+
+#[test]
+fn a_paused_ai_answers_requests_but_only_searches_once_resumed() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	ai.send(Request::Pause);
+	ai.send(Request::Go { limits: sims(200) });
+
+	// requests are handled in order, so the search has been started by the time this is answered
+	assert_eq!(info(&ai).total_sims, 0);
+
+	ai.send(Request::Resume);
+	match ai.recv_blocking() {
+		Response::Done { best_move, sims, .. } => {
+			assert!(best_move.is_some());
+			assert!(sims >= 200);
+		},
+		_ => panic!("expected the search to finish once resumed"),
+	}
+}
+
+#[test]
+fn an_ai_can_be_dropped_after_quitting() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	ai.send(Request::Quit);
+	drop(ai);
+}
+
+/// `TicTacToe` with a handle that every copy of the position shares, so that a test can tell
+/// when no thread holds a position any more.
+#[derive(Clone)]
+struct Tracked {
+	game: TicTacToe,
+	handle: Arc<()>,
+}
+
+impl Game for Tracked {
+	type Move = (usize, usize);
+	type Player = Player;
+
+	fn available_moves(&self) -> Vec<(usize, usize)> {
+		self.game.available_moves()
+	}
+
+	fn make_move(&self, m: &(usize, usize)) -> Option<Box<Self>> {
+		self.game.make_move(m).map(|game| Box::new(Tracked { game: *game, handle: self.handle.clone() }))
+	}
+
+	fn make_move_mut(&mut self, m: &(usize, usize)) -> bool {
+		self.game.make_move_mut(m)
+	}
+
+	fn get_cur_player(&self) -> Player {
+		self.game.get_cur_player()
+	}
+
+	fn get_winner(&self) -> Option<Player> {
+		self.game.get_winner()
+	}
+
+	fn get_result(&self) -> Option<GameResult<Player>> {
+		self.game.get_result()
+	}
+
+	fn random_move<R: Rng>(&self, rand: &mut R) -> Option<(usize, usize)> {
+		self.game.random_move(rand)
+	}
+}
+
+#[test]
+fn dropping_an_ai_ends_its_search_and_threads() {
+	for &parallelism in &[Parallelism::Leaf, Parallelism::Tree, Parallelism::Root] {
+		let handle = Arc::new(());
+		let game = Tracked { game: TicTacToe::new(), handle: handle.clone() };
+		let ai = Ai::with_settings(game, Settings { threads: Some(2), parallelism, ..Settings::default() });
+		ai.send(Request::Go { limits: Limits::default() });
+		ai.send(Request::Info);
+		match ai.recv_blocking() {
+			Response::Info { .. } => {},
+			_ => panic!("expected info"),
+		}
+		// once the info is answered the search is running, and the tree and workers hold positions
+		assert!(Arc::strong_count(&handle) > 2, "{:?}", parallelism);
+
+		drop(ai);
+		// every thread has ended, and dropped its positions along with it
+		assert_eq!(Arc::strong_count(&handle), 1, "{:?}", parallelism);
+	}
+}
+
+#[test]
+fn search_plays_and_proves_a_winning_move() {
+	for &parallelism in &[Parallelism::Leaf, Parallelism::Tree, Parallelism::Root] {
+		let settings = Settings { seed: Some(1), threads: Some(2), parallelism, ..Settings::default() };
+		let (done, info) = search(position(O_WINS_WITH_2_2), settings, sims(200));
+		assert_eq!(done.best_move, Some((2, 2)));
+		assert_eq!(done.confidence, 1.0);
+		assert_eq!(info.proof, Some(Proof::Win));
+	}
+}
+
+#[test]
+fn search_proves_a_lost_position() {
+	// without the endgame solver, which would solve it straight away
+	let (done, info) = search(position(X_LOSES), Settings { seed: Some(1), endgame_moves: 0, ..Settings::default() }, sims(1000));
+	assert_eq!(done.confidence, 0.0);
+	assert_eq!(info.proof, Some(Proof::Loss));
+}
+
+/// Turns a result for the player to move in `game` into one for `player`. The player who ends the
+/// game stays the player to move.
+fn for_player(proof: Proof, game: &TicTacToe, player: Player) -> Proof {
+	match proof {
+		Proof::Win if game.get_cur_player() != player => Proof::Loss,
+		Proof::Loss if game.get_cur_player() != player => Proof::Win,
+		proof => proof,
+	}
+}
+
+/// The result for the player to move, worked out by trying every line, or `None` if that takes
+/// more than `budget` positions.
+fn minimax(game: &TicTacToe, seen: &mut HashMap<String, Proof>, budget: &mut usize) -> Option<Proof> {
+	let player = game.get_cur_player();
+	match game.get_result() {
+		Some(GameResult::Win(winner)) => return Some(if winner == player { Proof::Win } else { Proof::Loss }),
+		Some(GameResult::Draw) => return Some(Proof::Draw),
+		None => {},
+	}
+	if let Some(&proof) = seen.get(&game.to_notation()) {
+		return Some(proof);
+	}
+	if *budget == 0 {
+		return None;
+	}
+	*budget -= 1;
+
+	let mut best = Proof::Loss;
+	for mv in game.available_moves() {
+		let child = game.make_move(&mv).unwrap();
+		let proof = for_player(minimax(&child, seen, budget)?, &child, player);
+		if proof == Proof::Win || (proof == Proof::Draw && best == Proof::Loss) {
+			best = proof;
+		}
+	}
+
+	seen.insert(game.to_notation(), best);
+	Some(best)
+}
+
+#[test]
+fn solver_finds_a_winning_move() {
+	let solution = solve(&position(O_WINS_WITH_2_2), None).unwrap();
+	assert_eq!(solution.best_move, Some((2, 2)));
+	assert_eq!(solution.value, Proof::Win);
+}
+
+#[test]
+fn solver_proves_a_lost_position() {
+	assert_eq!(solve(&position(X_LOSES), None).unwrap().value, Proof::Loss);
+}
+
+#[test]
+fn solver_of_a_finished_game_has_no_move() {
+	let mut game = position("OOOXX1X2/OOOXX1X2/OO1XX4/9/9/9/9/9/9 OO7 2 O");
+	game.make_move_mut(&(2, 2));
+
+	// O, who won, is still the player to move
+	let solution = solve(&game, None).unwrap();
+	assert_eq!(solution.best_move, None);
+	assert_eq!(solution.value, Proof::Win);
+}
+
+#[test]
+fn solver_gives_up_after_its_node_limit() {
+	assert_eq!(solve(&TicTacToe::new(), Some(1000)), None);
+}
+
+#[test]
+fn solver_agrees_with_trying_every_line_in_random_games() {
+	let mut rand = XorShiftRng::from_seed([5, 6, 7, 8]);
+	let mut checked = 0;
+
+	for _ in 0..20 {
+		let mut game = TicTacToe::new();
+		let mut history = Vec::new();
+		while game.get_result().is_none() {
+			history.push(game.clone());
+			let mv = game.random_move(&mut rand).unwrap();
+			game.make_move_mut(&mv);
+		}
+
+		// from the end of the game backwards, until the positions get too big to try every line
+		for position in history.iter().rev() {
+			let mut seen = HashMap::new();
+			let expected = match minimax(position, &mut seen, &mut 2_000) {
+				Some(proof) => proof,
+				None => break,
+			};
+
+			let solution = solve(position, None).unwrap();
+			assert_eq!(solution.value, expected, "{}", position.to_notation());
+
+			// the move has to keep the result
+			let after = position.make_move(&solution.best_move.unwrap()).unwrap();
+			let kept = for_player(minimax(&after, &mut seen, &mut 2_000).unwrap(), &after, position.get_cur_player());
+			assert_eq!(kept, expected, "{}", position.to_notation());
+			checked += 1;
+		}
+	}
+
+	assert!(checked > 50);
+}
+
+#[test]
+fn ai_plays_the_solution_in_the_endgame() {
+	// every other board is drawn, so there are few enough moves left to solve
+	let game = position("OOOXX1X2/OOOXX1X2/OO1XX4/XOXXOOOXX/XOXXOOOXX/XOXXOOOXX/XOXXOOOXX/XOXXOOOXX/XOXXOOOXX OO7 2 O");
+
+	let (done, info) = search(game, Settings { seed: Some(1), ..Settings::default() }, sims(1000));
+	assert_eq!(done.best_move, Some((2, 2)));
+	assert_eq!(done.confidence, 1.0);
+	assert_eq!(done.sims, 0);
+	assert_eq!(info.proof, Some(Proof::Win));
+}
+
+#[test]
+fn search_ends_at_its_simulation_limit() {
+	let (done, _) = search(TicTacToe::new(), Settings { seed: Some(1), ..Settings::default() }, sims(300));
+	assert_eq!(done.sims, 300);
+}
+
+#[test]
+fn search_ends_once_it_has_added_enough_nodes() {
+	let limits = Limits { nodes: Some(500), ..Limits::default() };
+	let (done, _) = search(TicTacToe::new(), Settings { seed: Some(1), ..Settings::default() }, limits);
+	assert!(done.nodes >= 500, "{}", done.nodes);
+}
+
+#[test]
+fn search_ends_once_the_tree_is_deep_enough() {
+	let limits = Limits { depth: Some(4), ..Limits::default() };
+	let (done, _) = search(TicTacToe::new(), Settings { seed: Some(1), ..Settings::default() }, limits);
+	assert!(done.depth >= 4, "{}", done.depth);
+}
+
+#[test]
+fn search_ends_after_its_time() {
+	let limits = Limits { time: Some(Duration::from_millis(100)), ..Limits::default() };
+	let (done, _) = search(TicTacToe::new(), Settings { threads: Some(1), ..Settings::default() }, limits);
+	assert!(done.time >= Duration::from_millis(100) && done.time < Duration::from_secs(2), "{:?}", done.time);
+}
+
+#[test]
+fn search_ends_at_whichever_limit_comes_first() {
+	let limits = Limits { sims: Some(100), time: Some(Duration::from_secs(60)), ..Limits::default() };
+	let (done, _) = search(TicTacToe::new(), Settings { seed: Some(1), ..Settings::default() }, limits);
+	assert_eq!(done.sims, 100);
+	assert!(done.time < Duration::from_secs(60));
+}
+
+#[test]
+fn seeded_ais_only_search_when_asked() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	assert_eq!(info(&ai).total_sims, 0);
+
+	// nothing more is searched after the search that was asked for, however long the AI waits
+	go(&ai, sims(100));
+	assert_eq!(info(&ai).total_sims, 100);
+	make_move(&ai, (4, 4));
+	assert_eq!(info(&ai).total_sims, 100);
+}
+
+#[test]
+fn pondering_searches_between_requests() {
+	let ai = Ai::with_settings(TicTacToe::new(), Settings { threads: Some(1), ..Settings::default() });
+	// only returns once the AI has searched without being asked to
+	wait_for_sims(&ai, 1);
+}
+
+#[test]
+fn limited_pondering_stops_at_its_limit_on_each_position() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	ai.send(Request::SetPonder(Ponder::Limited(sims(300))));
+	assert_eq!(wait_for_sims(&ai, 300), 300);
+
+	make_move(&ai, (4, 4));
+	assert_eq!(wait_for_sims(&ai, 600), 600);
+}
+
+#[test]
+fn limited_pondering_is_replaced_by_a_longer_search() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	// handled together while paused, so the ponder search is still going when the search starts
+	ai.send(Request::Pause);
+	ai.send(Request::SetPonder(Ponder::Limited(sims(50))));
+	make_move(&ai, (4, 0));
+	ai.send(Request::Go { limits: sims(3000) });
+	ai.send(Request::Resume);
+	match ai.recv_blocking() {
+		Response::Done { sims, .. } => assert_eq!(sims, 3000),
+		_ => panic!("expected the search to finish"),
+	}
+
+	assert_eq!(go(&ai, sims(10)).sims, 10);
+	assert_eq!(info(&ai).total_sims, 3010);
+}
+
+#[test]
+fn making_a_move_keeps_the_tree_below_it() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	let best_move = go(&ai, sims(2000)).best_move.unwrap();
+	assert_eq!(info(&ai).tree_reuse, None);
+
+	make_move(&ai, best_move);
+	let reuse = info(&ai).tree_reuse.unwrap();
+	assert!(reuse.nodes_kept > 0 && reuse.nodes_kept < reuse.nodes_before, "{:?}", reuse);
+	assert!(reuse.sims_kept > 0);
+	assert!(reuse.fraction() > 0.0 && reuse.fraction() < 1.0);
+}
+
+#[test]
+fn draws_count_as_half_a_win() {
+	// only board 8 is left, and winning it can't make a line, so every game is drawn
+	let drawn = "XOXXOOOXX";
+	let game = position(&format!("{0}/{0}/{0}/{0}/{0}/{0}/{0}/{0}/9 9 8 X", drawn));
+
+	let nodes = NodeList::new(None, false);
+	let root = nodes.add(MoveTreeNode::new_root(game));
+	let pool = SimThreadPool::new(1, Some(1), Rollout::Random);
+	let mut rand = XorShiftRng::from_seed([1, 2, 3, 4]);
+	for _ in 0..50 {
+		montecarlo(&nodes, root, &pool, SimBudget::Count(4), 1.0, &mut rand);
+	}
+
+	let root = nodes.get(root);
+	assert_eq!(root.children.len(), 9);
+	for child in root.children.values() {
+		assert!(child.games > 0);
+		assert_eq!(child.wins, child.games as f64 / 2.0);
+	}
+}
+
+#[test]
+fn undo_goes_back_to_the_tree_from_before_the_move() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	let best_move = go(&ai, sims(2000)).best_move.unwrap();
+	let before = info(&ai);
+
+	make_move(&ai, best_move);
+	ai.send(Request::Undo);
+	match ai.recv_blocking() {
+		Response::Ok => {},
+		_ => panic!("expected the move to be taken back"),
+	}
+
+	// the statistics of every move are kept, though the other moves' subtrees only one level deep
+	let undone = info(&ai);
+	assert_eq!(undone.best_move, before.best_move);
+	assert!(undone.nodes > 81 && undone.nodes < before.nodes, "{} of {}", undone.nodes, before.nodes);
+
+	let reuse = undone.tree_reuse.unwrap();
+	assert_eq!(reuse.nodes_kept, undone.nodes);
+	assert_eq!(reuse.sims_kept, 2000);
+}
+
+#[test]
+fn undo_without_a_move_to_take_back_does_nothing() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	ai.send(Request::Undo);
+	match ai.recv_blocking() {
+		Response::Ok => {},
+		_ => panic!("expected an answer"),
+	}
+
+	let info = info(&ai);
+	assert_eq!((info.total_sims, info.tree_reuse), (0, None));
+}
+
+#[test]
+fn set_position_starts_again_with_an_empty_tree() {
+	let ai = Ai::new(TicTacToe::new(), Some(1));
+	go(&ai, sims(500));
+	let nodes_before = info(&ai).nodes;
+
+	ai.send(Request::SetPosition(position(O_WINS_WITH_2_2)));
+	match ai.recv_blocking() {
+		Response::Ok => {},
+		_ => panic!("expected the position to be set"),
+	}
+	let info = info(&ai);
+	assert_eq!((info.best_move, info.nodes), (None, 1));
+	assert_eq!(info.tree_reuse, Some(TreeReuse { nodes_before, nodes_kept: 0, sims_kept: 0 }));
+
+	assert_eq!(go(&ai, sims(200)).best_move, Some((2, 2)));
+}
+
+#[test]
+fn root_parallel_merges_every_trees_moves_from_the_root() {
+	let nodes = Arc::new(NodeList::new(None, false));
+	let root = nodes.add(MoveTreeNode::new_root(TicTacToe::new()));
+	let workers = RootWorkers::new(nodes.clone(), root, 3, Some(1), Rollout::Random, 1.0);
+	let (sims, _) = workers.search(root, SimBudget::Count(900));
+
+	let own = nodes.get(root).children.clone();
+	let mut merged = own.clone();
+	workers.merge(&mut merged);
+
+	// every simulation went through one move from the root in one of the trees
+	assert_eq!(merged.values().map(|child| child.games).sum::<u32>(), sims);
+	assert!(own.values().map(|child| child.games).sum::<u32>() < sims);
+	assert_eq!(merged.len(), 81);
+	for (mv, child) in &own {
+		assert!(merged[mv].games >= child.games);
+		assert!(merged[mv].wins >= child.wins);
+	}
+}
+
+#[test]
+fn root_parallel_merges_the_proofs_of_the_helper_trees() {
+	let nodes = Arc::new(NodeList::new(None, false));
+	let root = nodes.add(MoveTreeNode::new_root(position(O_WINS_WITH_2_2)));
+	let workers = RootWorkers::new(nodes.clone(), root, 2, Some(1), Rollout::Random, 1.0);
+	workers.search(root, SimBudget::Count(400));
+
+	// only the helper's own moves, which are added for the moves missing from the map
+	let mut merged = ChildMap::default();
+	workers.merge(&mut merged);
+	assert_eq!(merged.len(), nodes.get(root).children.len());
+	assert_eq!(merged[&(2, 2)].proof, Some(Proof::Win));
+}
+
+/// A tree that is a single line of `depth` nodes below its root, which is returned along with the
+/// IDs of the nodes below it.
+fn line_of_nodes(nodes: &NodeList<TicTacToe>, depth: usize) -> (NodeID, Vec<NodeID>) {
+	let root = nodes.add(MoveTreeNode::new_root(TicTacToe::new()));
+	let mut ids = Vec::new();
+
+	let mut parent = root;
+	for _ in 0..depth {
+		let id = nodes.add(MoveTreeNode::new(TicTacToe::new(), parent));
+		nodes.get(parent).children.insert((0, 0), Child::new(id, None));
+		ids.push(id);
+		parent = id;
+	}
+
+	(root, ids)
+}
+
+#[test]
+fn pruning_a_very_deep_tree_does_not_overflow_the_stack() {
+	let nodes = NodeList::new(None, false);
+	let (root, _) = line_of_nodes(&nodes, 200_000);
+	assert_eq!(nodes.len(), 200_001);
+
+	nodes.prune(root);
+	assert_eq!(nodes.len(), 1);
+	assert!(nodes.get(root).children.is_empty());
+}
+
+#[test]
+fn freed_nodes_make_room_for_new_ones() {
+	let nodes = NodeList::new(None, false);
+	let (root, ids) = line_of_nodes(&nodes, 1000);
+	nodes.prune(root);
+
+	// nodes go to the shards in turn, and every shard has at least 15 free slots
+	let freed: HashSet<NodeID> = ids.into_iter().collect();
+	for _ in 0..15 * SHARDS {
+		let id = nodes.add(MoveTreeNode::new(TicTacToe::new(), root));
+		assert!(freed.contains(&id), "{} wasn't freed", id);
+	}
+	assert_eq!(nodes.len(), 1 + 15 * SHARDS);
+	assert_eq!(nodes.added(), 1001 + 15 * SHARDS);
+}
+
+#[test]
+fn a_full_tree_is_pruned_and_keeps_growing() {
+	let settings = Settings { seed: Some(1), max_nodes: Some(500), ..Settings::default() };
+	let (done, info) = search(TicTacToe::new(), settings, sims(5000));
+
+	// more nodes than the tree can hold, since it makes room for new ones
+	assert!(info.nodes <= 500, "{}", info.nodes);
+	assert!(done.nodes > 500, "{}", done.nodes);
+}
+
+#[test]
+fn a_full_tree_stops_growing_when_asked_to() {
+	let settings = Settings { seed: Some(1), max_nodes: Some(500), full_tree: FullTree::Stop, ..Settings::default() };
+	let (done, info) = search(TicTacToe::new(), settings, sims(5000));
+
+	// the last node to be expanded may take the tree past its limit
+	assert!(info.nodes < 500 + 81, "{}", info.nodes);
+	assert_eq!(done.nodes, info.nodes - 1);
+}
+
+#[test]
+fn shrinking_prunes_the_tree_back_to_three_quarters_of_its_limit() {
+	let nodes = NodeList::new(Some(1000), false);
+	let root = nodes.add(MoveTreeNode::new_root(TicTacToe::new()));
+	let pool = SimThreadPool::new(1, Some(1), Rollout::Random);
+	let mut rand = XorShiftRng::from_seed([1, 2, 3, 4]);
+	while !nodes.is_full() {
+		montecarlo(&nodes, root, &pool, SimBudget::Count(1), 1.0, &mut rand);
+	}
+	let games = nodes.get(root).games;
+
+	nodes.shrink(root);
+	assert!(nodes.len() <= 750, "{}", nodes.len());
+	assert_eq!(nodes.subtree_size(root), nodes.len());
+	// the moves from the root keep their statistics
+	let root = nodes.get(root);
+	assert_eq!(root.children.len(), 81);
+	assert_eq!(root.children.values().map(|child| child.games).sum::<u32>(), games);
+}
+
+#[test]
+fn info_reports_the_memory_of_the_tree_within_its_limit() {
+	let settings = Settings { seed: Some(1), max_memory: Some(1024 * 1024), ..Settings::default() };
+	let (_, info) = search(TicTacToe::new(), settings, sims(20_000));
+
+	assert!(info.memory <= 1024 * 1024, "{}", info.memory);
+	assert_eq!(info.memory % info.nodes, 0);
+	assert!(info.memory / info.nodes > mem::size_of::<MoveTreeNode<TicTacToe>>());
+}
@@ -1,16 +1,61 @@
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
 
 use super::Game;
 use super::ai::*;
 
+#[derive(Clone)]
 pub(in super) struct Child {
 	pub games: u32,
-	pub wins: u32,
+	/// Wins count as one point and draws as half a point.
+	pub wins: f64,
 	pub uct: f64,
 	pub simulations: u32,
+	/// Tree-parallel iterations currently running through this move, which count as losses until
+	/// they finish so that other workers look elsewhere (a virtual loss).
+	pub in_flight: u32,
+	/// The result of the move for the player making it, once the search has proven it.
+	pub proof: Option<Proof>,
 	pub node: NodeID,
 }
 
+impl Child {
+	/// The entry for a move that hasn't been searched yet.
+	pub fn new(node: NodeID, proof: Option<Proof>) -> Self {
+		Child {
+			games: 0,
+			wins: 0.0,
+			uct: if proof == Some(Proof::Loss) { f64::NEG_INFINITY } else { f64::INFINITY },
+			simulations: 0,
+			in_flight: 0,
+			proof,
+			node,
+		}
+	}
+}
+
+/// Children are hashed with fixed keys so that they are always visited in the same order, which
+/// keeps seeded searches reproducible.
+pub(in super) type ChildMap<M> = HashMap<M, Child, BuildHasherDefault<DefaultHasher>>;
+
+/// The result of a position for the player to move, if its moves prove it: a win once any move
+/// wins, otherwise the best of the results once every move is proven.
+pub(in super) fn proven<M>(children: &ChildMap<M>) -> Option<Proof> {
+	let mut proof = if children.is_empty() { None } else { Some(Proof::Loss) };
+
+	for child in children.values() {
+		match child.proof {
+			Some(Proof::Win) => return Some(Proof::Win),
+			Some(Proof::Draw) => if proof.is_some() { proof = Some(Proof::Draw) },
+			Some(Proof::Loss) => {},
+			None => proof = None,
+		}
+	}
+
+	proof
+}
+
 pub(in super) struct MoveTreeNode<G: Game> {
 	pub game: G,
 	pub player: G::Player,
@@ -18,7 +63,12 @@ pub(in super) struct MoveTreeNode<G: Game> {
 	pub games: u32,
 	
 	pub parent: Option<NodeID>,
-	pub children: HashMap<G::Move, Child>,
+	pub children: ChildMap<G::Move>,
+	/// Set while a tree-parallel worker is adding the node's children.
+	pub expanding: bool,
+	/// The number of nodes that have this node as a child, which is more than one when a position
+	/// is reached by transposition.
+	pub refs: u32,
 }
 
 impl<G> MoveTreeNode<G> where G: Game {
@@ -31,7 +81,9 @@ impl<G> MoveTreeNode<G> where G: Game {
 			games: 0,
 
 			parent: None,
-			children: HashMap::new(),
+			children: ChildMap::default(),
+			expanding: false,
+			refs: 0,
 		}
 	}
 
@@ -44,7 +96,9 @@ impl<G> MoveTreeNode<G> where G: Game {
 			games: 0,
 
 			parent: Some(parent),
-			children: HashMap::new(),
+			children: ChildMap::default(),
+			expanding: false,
+			refs: 0,
 		}
 	}
 }
\ No newline at end of file
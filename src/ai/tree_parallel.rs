@@ -0,0 +1,285 @@
+use std::f64;
+use std::sync::Arc;
+use std::sync::mpsc::{ channel, Sender, Receiver };
+use std::thread::{ self, JoinHandle };
+use std::time::{ Instant, Duration };
+
+use rand::Rng;
+
+use super::{ Game, GameResult, Rollout };
+use super::tree::*;
+use super::ai::{ NodeID, NodeList, Proof };
+use super::random::new_rng;
+use super::sim_thread_pool::{ playout, SimBudget };
+
+/// Playouts run from each leaf a worker selects. More than one keeps the workers simulating most
+/// of the time instead of waiting for each other's locks, and keeps the tree from growing faster
+/// than it can be used.
+const LEAF_SIMS: u32 = 16;
+
+/// Picks the move to follow from `node`, counting every iteration still running through a move
+/// as `LEAF_SIMS` lost games. A proven win is always picked and a proven loss only if every move
+/// loses. Ties are broken at random.
+fn select_child<G: Game, R: Rng>(node: &MoveTreeNode<G>, exploration: f64, rand: &mut R) -> Option<(G::Move, NodeID, bool, Option<Proof>)> {
+    let in_flight: u32 = node.children.values().map(|child| child.in_flight).sum();
+    let total_games = (node.games + in_flight * LEAF_SIMS) as f64;
+
+    let mut best = None;
+    let mut best_uct = f64::NEG_INFINITY;
+    let mut ties = 0;
+
+    for (mv, child) in &node.children {
+        let games = child.games + child.in_flight * LEAF_SIMS;
+        let uct = match child.proof {
+            Some(Proof::Win) => f64::INFINITY,
+            Some(Proof::Loss) => f64::NEG_INFINITY,
+            _ if games == 0 => f64::INFINITY,
+            _ => child.wins / games as f64 + exploration * (total_games.ln() / games as f64).sqrt(),
+        };
+
+        if uct > best_uct {
+            best_uct = uct;
+            ties = 1;
+            best = Some((mv, child));
+        } else if uct == best_uct {
+            // keeps each of the tied moves with equal probability
+            ties += 1;
+            if rand.gen_range(0, ties) == 0 {
+                best = Some((mv, child));
+            }
+        }
+    }
+
+    best.map(|(mv, child)| (mv.clone(), child.node, child.games == 0, child.proof))
+}
+
+/// Gives `node` a child for every move. Returns `false` if another worker is already doing so,
+/// there are no moves or the tree is full.
+fn expand<G: Game>(nodes: &NodeList<G>, node_id: NodeID) -> bool {
+    if nodes.is_full() {
+        return !nodes.get(node_id).children.is_empty();
+    }
+
+    let game = {
+        let mut node = nodes.get(node_id);
+        if node.expanding || !node.children.is_empty() {
+            return !node.children.is_empty();
+        }
+        node.expanding = true;
+        node.game.clone()
+    };
+
+    // the children are created without holding the parent, whose shard they may share
+    let player = game.get_cur_player();
+    let children: Vec<(G::Move, Child)> = game.available_moves().into_iter()
+        .map(|mv| {
+            let child = *game.make_move(&mv).unwrap();
+            let proof = Proof::of_game(&child, &player);
+            (mv, Child::new(nodes.add_child(child, node_id), proof))
+        })
+        .collect();
+
+    let mut node = nodes.get(node_id);
+    node.expanding = false;
+    node.children.extend(children);
+
+    !node.children.is_empty()
+}
+
+/// Runs one iteration of the search from `root` with at most `max_sims` playouts, returning the
+/// number of simulations run and the depth of the leaf they were run from. Reaching a move whose
+/// result is proven counts as one game with that result instead, and proofs are passed up the
+/// path on the way back.
+fn iterate<G: Game, R: Rng>(nodes: &NodeList<G>, root: NodeID, max_sims: u32, exploration: f64, rollout: Rollout, rand: &mut R) -> (u32, usize) {
+    let mut path = Vec::new();
+    let mut node_id = root;
+    let mut proven_leaf = None;
+
+    // Select, adding a virtual loss to every move on the way down. A move that hasn't been
+    // simulated yet is simulated from; a leaf that has is expanded first.
+    let game = loop {
+        let selected = {
+            let node = nodes.get(node_id);
+            if node.children.is_empty() {
+                None
+            } else {
+                select_child(&node, exploration, rand).map(|selected| (selected, node.player.clone()))
+            }
+        };
+
+        match selected {
+            Some(((mv, child, unvisited, proof), player)) => {
+                nodes.get(node_id).children.get_mut(&mv).unwrap().in_flight += 1;
+                path.push((node_id, mv));
+                node_id = child;
+
+                if let Some(proof) = proof {
+                    // the result is known, so there is nothing to simulate
+                    proven_leaf = Some((player, proof));
+                    break None;
+                }
+                if unvisited {
+                    break Some(nodes.get(node_id).game.clone());
+                }
+            },
+            // a leaf that can't be expanded, because the game is over, another worker is
+            // expanding it or the tree is full, is simulated from instead
+            None => if !expand(nodes, node_id) {
+                break Some(nodes.get(node_id).game.clone());
+            },
+        }
+    };
+
+    // Simulate
+    let results: Vec<GameResult<G::Player>> = match game {
+        Some(game) => (0..max_sims.max(1)).map(|_| playout(&game, rollout, rand)).collect(),
+        None => Vec::new(),
+    };
+    let sims = (results.len() as u32).max(1);
+
+    // Backprop from the leaf up, taking the virtual losses back off. Only the path actually taken
+    // is updated: the other parents of a transposed node keep their own statistics for the move
+    // to it. `proof` is the result just proven for the player to move below, if there is one.
+    let depth = path.len();
+    let mut proof = None;
+    let mut deepest = true;
+    for (node_id, mv) in path.into_iter().rev() {
+        let mut node = nodes.get(node_id);
+        let player = node.player.clone();
+        let score: f64 = match proven_leaf {
+            Some((ref prover, proof)) => proof.score_for(prover, &player),
+            None => results.iter().map(|result| result.score(&player)).sum(),
+        };
+
+        node.games += sims;
+        {
+            let child = node.children.get_mut(&mv).unwrap();
+            if let Some((ref below, proof)) = proof {
+                child.proof = Some(if *below == player { proof } else { proof.flip() });
+            }
+
+            child.games += sims;
+            child.wins += score;
+            child.simulations += 1;
+            child.in_flight -= 1;
+        }
+
+        // the deepest node may have just been expanded into moves that end the game, otherwise a
+        // node can only be proven once the node below it is
+        proof = if proof.is_some() || deepest {
+            proven(&node.children).map(|proof| (player, proof))
+        } else {
+            None
+        };
+        deepest = false;
+    }
+
+    (sims, depth)
+}
+
+/// Searches on several threads at once. Usually the workers share a single tree (tree
+/// parallelisation); each one selects, expands, simulates and backs up on its own, so the tree
+/// grows as fast as the workers can simulate rather than by one leaf per batch.
+pub(in super) struct TreeWorkers<G: Game> {
+    /// The tree each worker searches.
+    trees: Vec<Arc<NodeList<G>>>,
+    senders: Vec<Sender<(NodeID, SimBudget)>>,
+    receivers: Vec<Receiver<(u32, usize)>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<G> TreeWorkers<G> where G: Game + 'static {
+    /// Starts `num_threads` workers on `nodes`.
+    pub fn new(nodes: Arc<NodeList<G>>, num_threads: usize, seed: Option<u64>, rollout: Rollout, exploration: f64) -> Self {
+        TreeWorkers::with_trees(vec![nodes; num_threads], seed, rollout, exploration)
+    }
+
+    /// Starts a worker for each of `trees`, which may be the same tree more than once. With a
+    /// seed, worker `i` uses random stream `i + 1`.
+    pub fn with_trees(trees: Vec<Arc<NodeList<G>>>, seed: Option<u64>, rollout: Rollout, exploration: f64) -> Self {
+        let mut workers = TreeWorkers {
+            trees: trees.clone(),
+            senders: Vec::new(),
+            receivers: Vec::new(),
+            threads: Vec::new(),
+        };
+
+        for (i, nodes) in trees.into_iter().enumerate() {
+            let (to_thread, from_outside) = channel::<(NodeID, SimBudget)>();
+            let (to_outside, from_thread) = channel();
+
+            let thread = thread::spawn(move || {
+                let mut rand = new_rng(seed, i as u64 + 1);
+
+                loop {
+                    let (root, budget) = match from_outside.recv() {
+                        Ok(msg) => msg,
+                        Err(_) => return, // the workers were dropped
+                    };
+                    let start = Instant::now();
+
+                    let (mut sims, mut depth) = (0, 0);
+                    loop {
+                        let max_sims = match budget {
+                            SimBudget::Time(time) if start.elapsed() < Duration::from_millis(time) => LEAF_SIMS,
+                            SimBudget::Count(count) if sims < count => LEAF_SIMS.min(count - sims),
+                            _ => break,
+                        };
+
+                        let (s, d) = iterate(&nodes, root, max_sims, exploration, rollout, &mut rand);
+                        sims += s;
+                        depth = depth.max(d);
+                    }
+
+                    to_outside.send((sims, depth)).unwrap();
+                }
+            });
+
+            workers.senders.push(to_thread);
+            workers.receivers.push(from_thread);
+            workers.threads.push(thread);
+        }
+
+        workers
+    }
+
+    /// Searches from `root` on every worker until the budget is used up, returning the number of
+    /// simulations run and the depth of the deepest leaf. The tree isn't touched once this returns.
+    pub fn search(&self, root: NodeID, budget: SimBudget) -> (u32, usize) {
+        self.search_from(&vec![root; self.senders.len()], budget)
+    }
+
+    /// Like `search`, but with each worker starting from its own root in its own tree.
+    pub fn search_from(&self, roots: &[NodeID], budget: SimBudget) -> (u32, usize) {
+        let num_threads = self.senders.len() as u32;
+        for (i, (thread, &root)) in self.senders.iter().zip(roots).enumerate() {
+            debug_assert!(self.trees[i].len() > 0, "searching an empty tree");
+
+            let thread_budget = match budget {
+                SimBudget::Time(time) => SimBudget::Time(time),
+                SimBudget::Count(count) => {
+                    let extra = if (i as u32) < count % num_threads { 1 } else { 0 };
+                    SimBudget::Count(count / num_threads + extra)
+                },
+            };
+
+            thread.send((root, thread_budget)).unwrap();
+        }
+
+        self.receivers.iter()
+            .map(|thread| thread.recv().unwrap())
+            .fold((0, 0), |(sims, depth), (s, d)| (sims + s, depth.max(d)))
+    }
+}
+
+impl<G: Game> Drop for TreeWorkers<G> {
+    fn drop(&mut self) {
+        // closing the channels makes each worker return once it finishes its current search
+        self.senders.clear();
+
+        for thread in self.threads.drain(..) {
+            // a thread that panicked has already said so; see `Ai`'s drop
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Plays a match between two engine configurations, A and B, and estimates the Elo difference
+//! between them.
+
+extern crate tictactoe;
+extern crate rand;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use rand::{ weak_rng, Rng, SeedableRng, XorShiftRng };
+
+use tictactoe::game::{ self, GameRecord, Player, TicTacToe };
+use tictactoe::ai::{ Ai, Game, GameResult, Limits, Ponder, Request, Response, Settings };
+
+const USAGE: &str = "\
+Usage: arena [--games N] [--sims N] [--time MS] [--openings PLIES] [--seed SEED]
+             [--sprt ELO0 ELO1] [--records DIR] [--a-SETTING VALUE] [--b-SETTING VALUE]
+
+  --games N          games to play, alternating which engine moves first (default: 100)
+  --sims N           simulations per move (default: 2000, unless --time is given)
+  --time MS          milliseconds per move
+  --openings PLIES   start each pair of games from the same random opening of
+                     PLIES moves, up to 80, once with each engine moving first
+                     (default: 0)
+  --seed SEED        make the openings and both engines deterministic
+  --sprt ELO0 ELO1   stop as soon as a sequential probability ratio test decides
+                     between A being ELO0 or ELO1 stronger than B, with 5% error
+                     rates
+  --records DIR      write each game's record to DIR
+
+Engine settings are `seed`, `threads`, `exploration`, `rollout` (`random` or
+`win-first`), `parallelism` (`tree`, `root` or `leaf`), `max-nodes`, `memory`
+(in megabytes), `full-tree` (`stop` or `prune`), `transpositions` (`on` or
+`off`) and `endgame-moves`, given for engine A as e.g. `--a-exploration 0.5`
+and for engine B as `--b-...`. Both engines default to the normal settings,
+and --seed overrides the seed given to either.";
+
+/// The normal approximation's 95% interval is this many standard errors either side.
+const Z_95: f64 = 1.96;
+/// The chance of the SPRT accepting the wrong hypothesis, both ways.
+const SPRT_ERROR: f64 = 0.05;
+/// The longest opening that can leave the game unfinished, since every cell is taken after 81.
+const MAX_OPENING: usize = 80;
+/// Random openings tried before giving up on finding one that doesn't end the game, which random
+/// games seldom last long enough for past 70 moves.
+const OPENING_TRIES: u32 = 100_000;
+
+struct Options {
+	games: u32,
+	limits: Limits,
+	openings: usize,
+	seed: Option<u64>,
+	sprt: Option<(f64, f64)>,
+	records: Option<PathBuf>,
+	a: Settings,
+	b: Settings,
+}
+
+fn parse_args<I: Iterator<Item=String>>(mut args: I) -> Result<Options, String> {
+	let mut opts = Options {
+		games: 100,
+		limits: Limits::default(),
+		openings: 0,
+		seed: None,
+		sprt: None,
+		records: None,
+		a: Settings::default(),
+		b: Settings::default(),
+	};
+
+	while let Some(arg) = args.next() {
+		let mut number = |what: &str| args.next()
+			.and_then(|v| v.parse::<u64>().ok())
+			.ok_or_else(|| format!("{} expects {}", arg, what));
+
+		match arg.as_str() {
+			"-h" | "--help" => {
+				println!("{}", USAGE);
+				process::exit(0);
+			},
+			"--games" => opts.games = number("a number of games")? as u32,
+			"--sims" => opts.limits.sims = Some(number("a number of simulations")?),
+			"--time" => opts.limits.time = Some(Duration::from_millis(number("a number of milliseconds")?)),
+			"--openings" => opts.openings = match number("a number of moves up to 80")? as usize {
+				plies if plies <= MAX_OPENING => plies,
+				_ => return Err(format!("{} expects a number of moves up to {}", arg, MAX_OPENING)),
+			},
+			"--seed" => opts.seed = Some(number("a whole number")?),
+			"--records" => opts.records = Some(args.next().ok_or("--records expects a directory")?.into()),
+			"--sprt" => {
+				let mut bound = || args.next().and_then(|v| v.parse::<f64>().ok());
+				opts.sprt = match (bound(), bound()) {
+					(Some(elo0), Some(elo1)) if elo0 < elo1 => Some((elo0, elo1)),
+					_ => return Err("--sprt expects two Elo differences, the smaller first".to_owned()),
+				};
+			},
+			flag if flag.starts_with("--a-") || flag.starts_with("--b-") => {
+				let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+				let settings = if flag.starts_with("--a-") { &mut opts.a } else { &mut opts.b };
+				settings.set(&flag[4..], &value).map_err(|msg| format!("{} {}", flag, msg))?;
+			},
+			other => return Err(format!("Unknown argument: {}", other)),
+		}
+	}
+
+	if opts.games == 0 {
+		return Err("--games expects at least one game".to_owned());
+	}
+	if opts.limits == Limits::default() {
+		opts.limits.sims = Some(2000);
+	}
+
+	Ok(opts)
+}
+
+/// Wins, draws and losses from engine A's point of view.
+#[derive(Default)]
+struct Score {
+	wins: u32,
+	draws: u32,
+	losses: u32,
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+	1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn score_to_elo(score: f64) -> f64 {
+	-400.0 * (1.0 / score - 1.0).log10()
+}
+
+impl Score {
+	fn games(&self) -> u32 {
+		self.wins + self.draws + self.losses
+	}
+
+	/// The average points per game, counting a draw as half a win.
+	fn mean(&self) -> f64 {
+		(self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+	}
+
+	/// The variance of the points from a single game.
+	fn variance(&self) -> f64 {
+		let mean = self.mean();
+		let n = self.games() as f64;
+
+		(self.wins as f64 * (1.0 - mean).powi(2)
+			+ self.draws as f64 * (0.5 - mean).powi(2)
+			+ self.losses as f64 * mean.powi(2)) / n
+	}
+
+	/// The Elo difference and the margin of its 95% interval, or `None` while A has won or lost
+	/// every game, which says nothing about how large the difference is.
+	fn elo(&self) -> Option<(f64, f64)> {
+		let mean = self.mean();
+		if mean <= 0.0 || mean >= 1.0 {
+			return None;
+		}
+
+		let margin = Z_95 * (self.variance() / self.games() as f64).sqrt();
+		let low = score_to_elo((mean - margin).max(1e-6));
+		let high = score_to_elo((mean + margin).min(1.0 - 1e-6));
+		Some((score_to_elo(mean), (high - low) / 2.0))
+	}
+
+	/// The log-likelihood ratio of A being `elo1` rather than `elo0` stronger than B, using the
+	/// normal approximation to the distribution of the mean score.
+	fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+		let variance = self.variance();
+		if variance == 0.0 {
+			return 0.0;
+		}
+
+		let (s0, s1) = (elo_to_score(elo0), elo_to_score(elo1));
+		self.games() as f64 * (s1 - s0) * (2.0 * self.mean() - s0 - s1) / (2.0 * variance)
+	}
+
+	fn summary(&self) -> String {
+		let elo = match self.elo() {
+			Some((elo, margin)) => format!("{:+.1} +/- {:.1}", elo, margin),
+			None => "unknown".to_owned(),
+		};
+
+		format!("A vs B: {} - {} - {} [{:.3}] Elo {}", self.wins, self.losses, self.draws, self.mean(), elo)
+	}
+}
+
+/// Plays `plies` random moves from the start, trying again whenever the game ends early, up to
+/// `OPENING_TRIES` times.
+fn random_opening<R: Rng>(plies: usize, rng: &mut R) -> Result<Vec<(usize, usize)>, String> {
+	'retry: for _ in 0..OPENING_TRIES {
+		let mut game = TicTacToe::new();
+		let mut moves = Vec::new();
+
+		while moves.len() < plies {
+			match game.random_move(rng) {
+				Some(mv) if game.get_result().is_none() => {
+					game.make_move_mut(&mv);
+					moves.push(mv);
+				},
+				_ => continue 'retry,
+			}
+		}
+
+		if game.get_result().is_none() {
+			return Ok(moves);
+		}
+	}
+
+	Err(format!("No random opening of {} moves left the game unfinished in {} tries; try a shorter one.", plies, OPENING_TRIES))
+}
+
+/// Plays one game after `opening`, with `x` and `o` as the engines for each side.
+fn play_game(opening: &[(usize, usize)], x: Settings, o: Settings, limits: &Limits) -> (GameRecord, GameResult<Player>) {
+	let mut record = GameRecord::new(TicTacToe::new());
+	for &mv in opening {
+		record.push(mv);
+	}
+
+	let mut game = record.position();
+	let ais = [Ai::with_settings(game.clone(), x), Ai::with_settings(game.clone(), o)];
+	for ai in &ais {
+		ai.send(Request::SetPonder(Ponder::Off));
+	}
+
+	loop {
+		if let Some(result) = game.get_result() {
+			return (record, result);
+		}
+
+		let ai = &ais[if game.get_cur_player() == Player::X { 0 } else { 1 }];
+		ai.send(Request::Go { limits: limits.clone() });
+		let mv = loop {
+			if let Response::Done { best_move, .. } = ai.recv_blocking() {
+				break best_move.expect("an unfinished game always has a move");
+			}
+		};
+
+		game.make_move_mut(&mv);
+		record.push(mv);
+
+		for ai in &ais {
+			ai.make_move(mv);
+			while let Response::Info { .. } | Response::Done { .. } = ai.recv_blocking() {}
+		}
+	}
+}
+
+fn main() {
+	let opts = match parse_args(env::args().skip(1)) {
+		Ok(opts) => opts,
+		Err(msg) => {
+			eprintln!("{}\n\n{}", msg, USAGE);
+			process::exit(1);
+		}
+	};
+
+	if let Some(ref dir) = opts.records {
+		if let Err(e) = fs::create_dir_all(dir) {
+			eprintln!("Couldn't create {}: {}", dir.display(), e);
+			process::exit(1);
+		}
+	}
+
+	let mut rng = match opts.seed {
+		Some(seed) => XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9e37_79b9, 1]),
+		None => weak_rng(),
+	};
+	let mut score = Score::default();
+	let mut opening = Vec::new();
+
+	for i in 0..opts.games {
+		// each opening is played twice, once with each engine moving first
+		if i % 2 == 0 {
+			opening = match random_opening(opts.openings, &mut rng) {
+				Ok(opening) => opening,
+				Err(msg) => {
+					eprintln!("{}", msg);
+					process::exit(1);
+				},
+			};
+		}
+		let a_player = if i % 2 == 0 { Player::X } else { Player::O };
+
+		// --seed gives every engine in every game a seed of its own, over any given for A or B
+		let engine = |settings: &Settings, side: u64| Settings {
+			seed: opts.seed.map(|seed| seed.wrapping_add(i as u64 * 2 + side)).or(settings.seed),
+			..settings.clone()
+		};
+		let (a, b) = (engine(&opts.a, 0), engine(&opts.b, 1));
+		let (x, o) = if a_player == Player::X { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) };
+
+		let (record, result) = play_game(&opening, x, o, &opts.limits);
+		let outcome = match result {
+			GameResult::Win(winner) if winner == a_player => { score.wins += 1; "A wins" },
+			GameResult::Win(_) => { score.losses += 1; "B wins" },
+			GameResult::Draw => { score.draws += 1; "draw" },
+		};
+
+		let names = if a_player == Player::X { "A (X) vs B (O)" } else { "B (X) vs A (O)" };
+		println!("game {}: {}, {} after {} moves; {}", i + 1, names, outcome, record.moves().len(), score.summary());
+
+		if let Some(ref dir) = opts.records {
+			let path = dir.join(format!("game-{:04}.uttt", i + 1));
+			let text = format!("# game {}: {}, {}\n# A: {:?}\n# B: {:?}\n# opening: {}\n{}",
+				i + 1, names, outcome, a, b,
+				opening.iter().map(game::format_move).collect::<Vec<_>>().join(" "),
+				record.to_text());
+			if let Err(e) = fs::write(&path, text) {
+				eprintln!("Couldn't write {}: {}", path.display(), e);
+			}
+		}
+
+		if let Some((elo0, elo1)) = opts.sprt {
+			let llr = score.llr(elo0, elo1);
+			let lower = (SPRT_ERROR / (1.0 - SPRT_ERROR)).ln();
+			let upper = ((1.0 - SPRT_ERROR) / SPRT_ERROR).ln();
+
+			if llr <= lower || llr >= upper {
+				println!("SPRT: LLR {:.2} ({:.2}, {:.2}), accepted H{}: A is {} Elo stronger",
+					llr, lower, upper, if llr >= upper { 1 } else { 0 }, if llr >= upper { elo1 } else { elo0 });
+				break;
+			}
+		}
+	}
+
+	println!("\n{}", score.summary());
+	if let Some((elo0, elo1)) = opts.sprt {
+		println!("SPRT [{}, {}]: LLR {:.2}", elo0, elo1, score.llr(elo0, elo1));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!((actual - expected).abs() < 1e-3, "{} is not {}", actual, expected);
+	}
+
+	#[test]
+	fn elo_and_expected_score_convert_both_ways() {
+		assert_close(elo_to_score(0.0), 0.5);
+		assert_close(elo_to_score(400.0), 10.0 / 11.0);
+		assert_close(score_to_elo(0.75), 190.849);
+		assert_close(score_to_elo(elo_to_score(-123.0)), -123.0);
+	}
+
+	#[test]
+	fn elo_of_a_match_with_its_margin() {
+		let score = Score { wins: 6, draws: 2, losses: 2 };
+		assert_close(score.mean(), 0.7);
+		assert_close(score.variance(), 0.16);
+
+		let (elo, margin) = score.elo().unwrap();
+		assert_close(elo, 147.191);
+		assert_close(margin, 268.726);
+	}
+
+	#[test]
+	fn elo_is_unknown_while_one_engine_wins_every_game() {
+		assert_eq!(Score { wins: 5, draws: 0, losses: 0 }.elo(), None);
+		assert_eq!(Score { wins: 0, draws: 0, losses: 5 }.elo(), None);
+	}
+
+	#[test]
+	fn llr_of_an_even_match_favours_no_difference() {
+		let score = Score { wins: 300, draws: 400, losses: 300 };
+		assert_close(score.llr(0.0, 10.0), -0.690);
+		assert_close(score.llr(-10.0, 0.0), 0.690);
+	}
+
+	fn parse(args: &[&str]) -> Result<Options, String> {
+		parse_args(args.iter().map(|arg| arg.to_string()))
+	}
+
+	#[test]
+	fn openings_longer_than_a_game_can_last_are_rejected() {
+		assert_eq!(parse(&["--openings", "80"]).map(|opts| opts.openings), Ok(80));
+		assert_eq!(parse(&["--openings", "81"]).err(), Some("--openings expects a number of moves up to 80".to_owned()));
+		assert_eq!(parse(&["--openings"]).err(), Some("--openings expects a number of moves up to 80".to_owned()));
+	}
+
+	#[test]
+	fn an_opening_too_long_to_find_is_given_up_on() {
+		let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+		let opening = random_opening(20, &mut rng).unwrap();
+		assert_eq!(opening.len(), 20);
+		assert!(random_opening(80, &mut rng).is_err());
+	}
+
+	#[test]
+	fn llr_of_a_short_match() {
+		assert_close(Score { wins: 6, draws: 2, losses: 2 }.llr(0.0, 10.0), 0.173);
+		assert_eq!(Score { wins: 3, draws: 0, losses: 0 }.llr(0.0, 10.0), 0.0);
+	}
+}
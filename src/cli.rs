@@ -0,0 +1,118 @@
+use std::io::{ self, BufRead, Write };
+
+use tictactoe::game::{ TicTacToe, Player };
+use tictactoe::ai::{ Ai, Game, GameResult, Limits, Request, Response };
+use config::Config;
+
+fn parse_move(line: &str) -> Option<(usize, usize)> {
+	let nums: Vec<usize> = line.split(|c: char| !c.is_ascii_digit())
+		.filter_map(|s| s.parse().ok())
+		.collect();
+
+	match nums.as_slice() {
+		&[a, b] if a < 9 && b < 9 => Some((a, b)),
+		_ => None,
+	}
+}
+
+/// Prompts until the human enters a legal move. Returns `None` once stdin is closed.
+fn read_move<I>(game: &TicTacToe, lines: &mut I) -> Option<(usize, usize)>
+	where I: Iterator<Item=io::Result<String>>
+{
+	let moves = game.available_moves();
+
+	loop {
+		print!("{:?} to move> ", game.get_cur_player());
+		io::stdout().flush().unwrap();
+
+		let line = match lines.next() {
+			Some(Ok(line)) => line,
+			_ => return None,
+		};
+
+		match parse_move(&line) {
+			Some(mv) if moves.contains(&mv) => return Some(mv),
+			Some(mv) => println!("{:?} is not a legal move.", mv),
+			None => println!("Enter a move as `board cell`, e.g. `4 0`."),
+		}
+	}
+}
+
+/// Lets the AI search within `limits` and returns the best move it found.
+fn think(ai: &Ai<TicTacToe>, limits: Limits) -> Option<(usize, usize)> {
+	ai.send(Request::Info);
+	if let Response::Info { tree_reuse: Some(reuse), .. } = ai.recv_blocking() {
+		if reuse.sims_kept > 0 {
+			println!("AI kept {} of {} nodes ({}%) and {} simulations from pondering",
+				reuse.nodes_kept, reuse.nodes_before, (reuse.fraction()*100.0).floor(), reuse.sims_kept);
+		}
+	}
+
+	ai.send(Request::Go { limits });
+
+	loop {
+		if let Response::Done { best_move, confidence, sims, time_elapsed, .. } = ai.recv_blocking() {
+			if let Some(mv) = best_move {
+				let secs = time_elapsed.as_secs() as f64 + (time_elapsed.subsec_nanos() as f64 / 1_000_000_000.0);
+				println!("AI plays {:?} (confidence {}%, {} simulations, {} sims/second)",
+					mv, (confidence*100.0).floor(), sims, (sims as f64 / secs).floor());
+			}
+
+			return best_move;
+		}
+	}
+}
+
+pub fn run(config: &Config) {
+	let mut game = TicTacToe::new();
+
+	// each side the AI plays gets its own AI, so that the two can be set up differently
+	let ais: Vec<(Player, Ai<TicTacToe>)> = [Player::X, Player::O].iter()
+		.filter(|&&player| !config.is_human(player))
+		.map(|&player| (player, Ai::with_settings(game.clone(), config.side(player).ai.clone())))
+		.collect();
+	if let Some(ponder) = config.ponder_against(&config.humans) {
+		for (_, ai) in &ais {
+			ai.send(Request::SetPonder(ponder.clone()));
+		}
+	}
+
+	let stdin = io::stdin();
+	let mut lines = stdin.lock().lines();
+
+	loop {
+		println!("\n{}", game.to_str());
+
+		match game.get_result() {
+			Some(GameResult::Win(winner)) => {
+				println!("{:?} wins!", winner);
+				break;
+			},
+			Some(GameResult::Draw) => {
+				println!("Draw!");
+				break;
+			},
+			None => {},
+		}
+
+		let player = game.get_cur_player();
+		let mv = match ais.iter().find(|&&(p, _)| p == player) {
+			Some((_, ai)) => match think(ai, config.side(player).turn_limits()) {
+				Some(mv) => mv,
+				None => break,
+			},
+			None => match read_move(&game, &mut lines) {
+				Some(mv) => mv,
+				None => break,
+			},
+		};
+
+		game.make_move_mut(&mv);
+		for (_, ai) in &ais {
+			ai.make_move(mv);
+
+			// wait for the AI thread to move its tree to the new position
+			while let Response::Info { .. } | Response::Done { .. } = ai.recv_blocking() {}
+		}
+	}
+}
@@ -0,0 +1,347 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use toml::Value;
+
+use tictactoe::game::Player;
+use tictactoe::ai::{ Limits, Ponder, Settings };
+
+/// The config file that is read from the working directory when `--config` isn't given.
+const DEFAULT_CONFIG: &str = "tictactoe.toml";
+
+pub const USAGE: &str = "\
+Usage: tictactoe [--cli | --utttp] [--config FILE] [--human X|O|both|none | --ai-vs-ai]
+                 [--time SECONDS] [--seed SEED] [--ponder on|off|SIMS]
+                 [--threads N] [--exploration C] [--rollout random|win-first]
+                 [--parallelism tree|root|leaf] [--max-nodes N] [--memory MB]
+                 [--full-tree stop|prune] [--transpositions on|off]
+                 [--endgame-moves N]
+                 [--x-SETTING VALUE] [--o-SETTING VALUE]
+
+  --cli            play in the terminal instead of the graphical viewer
+  --utttp          speak the engine protocol on stdin and stdout
+  --config FILE    read settings from FILE (default: tictactoe.toml, if it exists)
+  --human SIDES    sides played by a human: X, O, both or none (default: X)
+  --ai-vs-ai       let the AI play both sides, the same as `--human none`
+  --time SECONDS   time the AI spends on each move (default: 10)
+  --seed SEED      make the AI deterministic
+  --ponder MODE    whether the AI thinks during the other side's turn: `on`,
+                   `off`, or a number of simulations to stop after (default: on,
+                   or off with --seed or when the AI plays both sides)
+  --threads N      simulation threads (default: one per CPU, or one with --seed)
+  --exploration C  UCT exploration constant (default: 1.414)
+  --rollout MODE   how playouts pick moves: `random`, or `win-first` to always
+                   take a move that wins the game (default: random)
+  --parallelism M  how the threads share the search: `tree` to all grow the same
+                   tree, `root` to each grow their own tree and combine the
+                   results for the moves from the root, or `leaf` to only run
+                   the playouts from leaves picked by one thread (default: tree)
+  --max-nodes N    the most nodes the search tree may hold (default: no limit)
+  --memory MB      roughly the most memory the search tree may use (default: no
+                   limit)
+  --full-tree M    what the AI does once the tree is full: `stop` adding nodes,
+                   or `prune` the least visited parts of it (default: prune)
+  --transpositions on|off
+                   whether a position reached by different move orders shares
+                   one node in the search tree (default: off)
+  --endgame-moves N
+                   solve positions with at most N empty cells left to play
+                   exactly, when that is quick enough, instead of searching
+                   them; 0 turns this off (default: 16)
+
+Each side played by the AI has its own AI, so every setting apart from --human
+and --ponder can also be given for one side only by starting it with `x-` or
+`o-`, e.g. `--o-exploration 0.5`. Later flags override earlier ones.
+
+The config file takes the same settings as TOML keys without the dashes, e.g.
+`time = 5` or `rollout = \"win-first\"`, and settings for one side in `[x]` and
+`[o]` tables; flags override the file.
+
+Moves are entered as two numbers from 0 to 8: the small board, then the cell
+within it, both counted left to right and top to bottom (e.g. `4 0`).";
+
+/// How the AI plays one side.
+#[derive(Clone)]
+pub struct Side {
+	/// Seconds the AI thinks for on each of its moves.
+	pub turn_time: u64,
+	pub ai: Settings,
+}
+
+impl Default for Side {
+	fn default() -> Side {
+		Side {
+			turn_time: 10,
+			ai: Settings::default(),
+		}
+	}
+}
+
+impl Side {
+	/// The limits for a search on this side's turn.
+	pub fn turn_limits(&self) -> Limits {
+		Limits {
+			time: Some(Duration::from_secs(self.turn_time)),
+			..Limits::default()
+		}
+	}
+
+	/// Sets one of the settings that can differ between the sides. Errors start with `key`, the
+	/// name the setting was given as.
+	fn set(&mut self, name: &str, key: &str, value: &str) -> Result<(), String> {
+		match name {
+			"time" => {
+				self.turn_time = match value.parse() {
+					Ok(t) if t > 0 => t,
+					_ => return Err(format!("{} expects a whole number of seconds above 0", key)),
+				};
+			},
+			_ => self.ai.set(name, value).map_err(|msg| format!("{} {}", key, msg))?,
+		}
+
+		Ok(())
+	}
+}
+
+/// Settings shared by the front ends.
+#[derive(Clone)]
+pub struct Config {
+	/// The sides played by a human; the AI plays the others.
+	pub humans: Vec<Player>,
+	pub ponder: Option<Ponder>,
+	pub x: Side,
+	pub o: Side,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			humans: vec![Player::X],
+			ponder: None,
+			x: Side::default(),
+			o: Side::default(),
+		}
+	}
+}
+
+impl Config {
+	pub fn is_human(&self, player: Player) -> bool {
+		self.humans.contains(&player)
+	}
+
+	pub fn side(&self, player: Player) -> &Side {
+		match player {
+			Player::X => &self.x,
+			Player::O => &self.o,
+		}
+	}
+
+	/// What the AIs do during the other side's turn when `humans` play the sides given, or `None`
+	/// to leave them as they start. Unless `--ponder` was given, AIs playing each other don't
+	/// ponder, since the pondering AI would take the CPUs from the one whose turn it is.
+	pub fn ponder_against(&self, humans: &[Player]) -> Option<Ponder> {
+		match self.ponder {
+			Some(ref ponder) => Some(ponder.clone()),
+			None if humans.is_empty() => Some(Ponder::Off),
+			None => None,
+		}
+	}
+
+	/// Reads the settings from the config file, if there is one, and then from the command line.
+	/// Exits after printing the usage if asked for `--help`.
+	pub fn load() -> Result<Config, String> {
+		let args: Vec<String> = env::args().skip(1).collect();
+		let mut config = Config::default();
+
+		match args.iter().position(|arg| arg == "--config") {
+			Some(i) => match args.get(i + 1) {
+				Some(path) => config.read_file(path)?,
+				None => return Err("--config expects a file".to_owned()),
+			},
+			None => if Path::new(DEFAULT_CONFIG).exists() {
+				config.read_file(DEFAULT_CONFIG)?;
+			},
+		}
+
+		config.read_args(&args)?;
+		Ok(config)
+	}
+
+	fn read_file(&mut self, path: &str) -> Result<(), String> {
+		let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+		self.read_toml(&text).map_err(|msg| format!("{}: {}", path, msg))
+	}
+
+	/// Reads the settings from the text of a config file.
+	fn read_toml(&mut self, text: &str) -> Result<(), String> {
+		let table = match text.parse::<Value>() {
+			Ok(Value::Table(table)) => table,
+			Ok(_) => return Err("expected a table of settings".to_owned()),
+			Err(e) => return Err(e.to_string()),
+		};
+
+		// settings for both sides come first, so that the `[x]` and `[o]` tables override them
+		for (key, value) in &table {
+			if let Value::Table(_) = *value {
+				continue;
+			}
+			self.set(key, &value_text(key, value)?)?;
+		}
+
+		for (key, value) in &table {
+			let side = match *value {
+				Value::Table(ref side) => side,
+				_ => continue,
+			};
+			if key != "x" && key != "o" {
+				return Err(format!("[{}] is not a side, expected [x] or [o]", key));
+			}
+
+			for (name, value) in side {
+				let key = format!("{}-{}", key, name);
+				self.set(&key, &value_text(&key, value)?)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn read_args(&mut self, args: &[String]) -> Result<(), String> {
+		let mut args = args.iter();
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--cli" | "--utttp" => { /* only used to pick the front end */ },
+				"--config" => { args.next(); },
+				"-h" | "--help" => {
+					println!("{}", USAGE);
+					process::exit(0);
+				},
+				"--ai-vs-ai" => self.humans.clear(),
+				flag if flag.starts_with("--") => {
+					let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+					self.set(&flag[2..], value).map_err(|msg| format!("--{}", msg))?;
+				},
+				other => return Err(format!("Unknown argument: {}", other)),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Sets one option from its name, without dashes, and value. Errors start with the name.
+	fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+		match key {
+			"human" => {
+				self.humans = match value {
+					"X" | "x" => vec![Player::X],
+					"O" | "o" => vec![Player::O],
+					"both" => vec![Player::X, Player::O],
+					"none" => vec![],
+					_ => return Err(format!("{} expects X, O, both or none", key)),
+				};
+			},
+			"ponder" => {
+				self.ponder = Some(match value {
+					"on" => Ponder::On,
+					"off" => Ponder::Off,
+					sims => Ponder::Limited(Limits {
+						sims: Some(sims.parse().map_err(|_| format!("{} expects on, off or a number of simulations", key))?),
+						..Limits::default()
+					}),
+				});
+			},
+			_ => if let Some(name) = key.strip_prefix("x-") {
+				self.x.set(name, key, value)?;
+			} else if let Some(name) = key.strip_prefix("o-") {
+				self.o.set(name, key, value)?;
+			} else {
+				self.x.set(key, key, value)?;
+				self.o.set(key, key, value)?;
+			},
+		}
+
+		Ok(())
+	}
+}
+
+/// Reads a setting from the config file as text, so that it goes through the same parsing as the
+/// command line. `true` and `false` are read as `on` and `off`.
+fn value_text(key: &str, value: &Value) -> Result<String, String> {
+	match *value {
+		Value::String(ref s) => Ok(s.clone()),
+		Value::Integer(n) => Ok(n.to_string()),
+		Value::Float(f) => Ok(f.to_string()),
+		Value::Boolean(b) => Ok(if b { "on" } else { "off" }.to_owned()),
+		_ => Err(format!("`{}` should be a string, a number or a boolean", key)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn read(text: &str) -> Result<Config, String> {
+		let mut config = Config::default();
+		config.read_toml(text)?;
+		Ok(config)
+	}
+
+	#[test]
+	fn side_tables_override_settings_for_both_sides() {
+		let config = read("time = 5\nexploration = 1.0\n[o]\nexploration = 0.5\n").unwrap();
+
+		assert_eq!((config.x.turn_time, config.o.turn_time), (5, 5));
+		assert_eq!(config.x.ai.exploration, 1.0);
+		assert_eq!(config.o.ai.exploration, 0.5);
+	}
+
+	#[test]
+	fn booleans_are_read_as_on_and_off() {
+		let config = read("transpositions = true\nponder = false\n[o]\ntranspositions = false\n").unwrap();
+
+		assert!(config.x.ai.transpositions);
+		assert!(!config.o.ai.transpositions);
+		assert_eq!(config.ponder, Some(Ponder::Off));
+	}
+
+	#[test]
+	fn bad_values_are_reported_with_their_key() {
+		assert_eq!(read("threads = 0").err(), Some("threads expects a whole number above 0".to_owned()));
+		assert_eq!(read("[x]\ntime = 0").err(), Some("x-time expects a whole number of seconds above 0".to_owned()));
+		assert_eq!(read("human = \"nobody\"").err(), Some("human expects X, O, both or none".to_owned()));
+		assert_eq!(read("rollout = [1]").err(), Some("`rollout` should be a string, a number or a boolean".to_owned()));
+		assert_eq!(read("colour = \"red\"").err(), Some("colour is not a setting".to_owned()));
+	}
+
+	#[test]
+	fn ais_playing_each_other_only_ponder_when_asked_to() {
+		let config = read("").unwrap();
+		assert_eq!(config.ponder_against(&[]), Some(Ponder::Off));
+		assert_eq!(config.ponder_against(&[Player::X]), None);
+
+		let config = read("ponder = 100").unwrap();
+		let limited = Ponder::Limited(Limits { sims: Some(100), ..Limits::default() });
+		assert_eq!(config.ponder_against(&[]), Some(limited.clone()));
+		assert_eq!(config.ponder_against(&[Player::O]), Some(limited));
+	}
+
+	#[test]
+	fn only_x_and_o_tables_are_sides() {
+		assert_eq!(read("[z]\ntime = 1").err(), Some("[z] is not a side, expected [x] or [o]".to_owned()));
+	}
+
+	#[test]
+	fn flags_take_a_value_and_override_the_file() {
+		let mut config = read("time = 5").unwrap();
+		let args: Vec<String> = ["--o-time", "3", "--ai-vs-ai"].iter().map(|s| s.to_string()).collect();
+		config.read_args(&args).unwrap();
+
+		assert_eq!((config.x.turn_time, config.o.turn_time), (5, 3));
+		assert!(config.humans.is_empty());
+		assert_eq!(config.read_args(&["--threads".to_owned()]).err(), Some("--threads expects a value".to_owned()));
+	}
+}
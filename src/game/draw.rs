@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+use cairo::Context;
+
+use ai::Game;
+use super::{ TicTacToe, Player };
+
+fn line(ctx: &Context, x1: f64, y1: f64, x2: f64, y2: f64) {
+	ctx.move_to(x1, y1);
+	ctx.line_to(x2, y2);
+	ctx.stroke();
+}
+
+fn draw_board(ctx: &Context, x1: f64, y1: f64, size: f64) {
+	ctx.save();
+	ctx.translate(x1, y1);
+
+	line(ctx, size/3.0,     0.0,          size/3.0,     size);
+	line(ctx, 2.0*size/3.0, 0.0,          2.0*size/3.0, size);
+	line(ctx, 0.0,          size/3.0,     size,         size/3.0);
+	line(ctx, 0.0,          2.0*size/3.0, size,         2.0*size/3.0);
+
+	ctx.restore();
+}
+
+impl TicTacToe {
+	pub fn draw(&self, ctx: &Context, w: f64, h: f64) {
+		let size = w.min(h) * 0.95;
+		ctx.translate(w/2.0 - size/2.0, h/2.0 - size/2.0);
+
+		if self.result.is_none() {
+			ctx.set_source_rgb(1.0, 1.0, 0.5);
+			if let Some(i) = self.move_restriction {
+				ctx.rectangle(size / 3.0 * (i % 3) as f64, size / 3.0 * (i / 3) as f64, size / 3.0, size / 3.0);
+				ctx.fill();
+			} else {
+				ctx.rectangle(0.0, 0.0, size, size);
+				ctx.fill();
+			}
+		}
+
+		ctx.set_source_rgb(0.0, 0.0, 0.0);
+		ctx.set_line_width(6.0);
+		draw_board(ctx, 0.0, 0.0, size);
+
+		ctx.set_line_width(2.0);
+
+		let board_size = size/3.0;
+		let cell_size = size/9.0;
+		for index_a in 0..9 {
+			let board_x = board_size * (index_a as f64 % 3.0).floor();
+			let board_y = board_size * (index_a as f64 / 3.0).floor();
+			ctx.save();
+			ctx.translate(board_x, board_y);
+
+			ctx.set_source_rgb(0.0, 0.0, 0.0);
+			draw_board(ctx, 0.0, 0.0, size/3.0);
+
+			for index_b in 0..9 {
+				let cell_x = cell_size * (index_b as f64 % 3.0).floor();
+				let cell_y = cell_size * (index_b as f64 / 3.0).floor();
+				ctx.save();
+				ctx.translate(cell_x + cell_size/2.0, cell_y + cell_size/2.0);
+
+				match self.cell(index_a, index_b) {
+					Some(Player::X) => {
+						let off = cell_size/2.0 * 0.8;
+						ctx.set_source_rgb(1.0, 0.0, 0.0);
+						line(ctx, -off, -off, off, off);
+						line(ctx, off, -off, -off, off);
+					},
+					Some(Player::O) => {
+						ctx.set_source_rgb(0.0, 0.0, 1.0);
+						ctx.arc(0.0, 0.0, cell_size/2.0 * 0.8, 0.0, 2.0*PI);
+						ctx.stroke();
+					},
+					None => { /* empty cell */ }
+				}
+
+				ctx.restore();
+			}
+
+			ctx.restore();
+		}
+
+		for index_a in 0..9 {
+			if let Some(player) = self.board_winner(index_a) {
+				ctx.save();
+				ctx.translate((index_a as f64 % 3.0).floor() * board_size + board_size/2.0, (index_a as f64 / 3.0).floor() * board_size + board_size/2.0);
+				ctx.set_line_width(6.0);
+
+				match player {
+					Player::X => {
+						let off = board_size/2.0 * 0.8;
+						ctx.set_source_rgb(1.0, 0.0, 0.0);
+						line(ctx, -off, -off, off, off);
+						line(ctx, off, -off, -off, off);
+					}
+					Player::O => {
+						ctx.set_source_rgb(0.0, 0.0, 1.0);
+						ctx.arc(0.0, 0.0, board_size/2.0 * 0.8, 0.0, 2.0*PI);
+						ctx.stroke();
+					}
+				}
+
+				ctx.restore();
+			}
+		}
+	}
+
+	pub fn click(&mut self, w: f64, h: f64, x: f64, y: f64) -> Option<(usize, usize)> {
+		let board_size = w.min(h) * 0.95;
+		let cell_size = board_size / 9.0;
+
+		let dx = w/2.0 - board_size/2.0;
+		let dy = h/2.0 - board_size/2.0;
+
+		let cell_x = ((x - dx) / cell_size).floor();
+		let cell_y = ((y - dy) / cell_size).floor();
+
+		let index_a = (cell_x / 3.0).floor() + 3.0 * (cell_y / 3.0).floor();
+		let index_b = (cell_x % 3.0).floor() + 3.0 * (cell_y % 3.0).floor();
+
+		let mv = (index_a as usize, index_b as usize);
+
+		if self.make_move_mut(&mv) {
+			Some(mv)
+		} else {
+			None
+		}
+	}
+}
+
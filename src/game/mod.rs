@@ -1,269 +1,464 @@
-use std::f64::consts::PI;
-use cairo::Context;
-use ai::Game;
+use rand::Rng;
 
+use ai::{ Game, GameResult };
+
+#[cfg(feature = "gui")]
+mod draw;
+mod notation;
+mod record;
+mod saved_game;
+
+pub use self::notation::START_NOTATION;
+pub use self::record::GameRecord;
+pub use self::saved_game::{ MoveStats, SavedGame };
+
+/// One of the two sides of a game. X always moves first.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Player {
 	X, O
 }
 
+impl Player {
+	fn index(self) -> usize {
+		match self {
+			Player::X => 0,
+			Player::O => 1,
+		}
+	}
+
+	pub fn other(self) -> Player {
+		match self {
+			Player::X => Player::O,
+			Player::O => Player::X,
+		}
+	}
+}
+
+/// Where a player may move after being sent to a small board that is already won or full.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum FreeMove {
+	/// Any empty cell in a board that is neither won nor full.
+	#[default]
+	AnyOpenBoard,
+	/// Any empty cell, even in boards that have already been won. Moves there don't change who
+	/// won the board.
+	AnyEmptyCell,
+}
+
+/// Variations on the Ultimate TicTacToe rules. The default is the standard rule set.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Rules {
+	/// A small board that fills up without a winner counts as won by both players when looking
+	/// for a line of boards.
+	pub drawn_boards_count_for_both: bool,
+	/// If every board is decided without a line, the player who won more boards wins instead of
+	/// the game being drawn.
+	pub majority_tiebreak: bool,
+	pub free_move: FreeMove,
+}
+
+impl Rules {
+	/// The names of the variants in play: `drawn-boards-count-for-both`, `majority-tiebreak` and
+	/// `any-empty-cell`. The standard rules have none.
+	pub fn variants(&self) -> Vec<&'static str> {
+		let mut variants = Vec::new();
+		if self.drawn_boards_count_for_both {
+			variants.push("drawn-boards-count-for-both");
+		}
+		if self.majority_tiebreak {
+			variants.push("majority-tiebreak");
+		}
+		if self.free_move == FreeMove::AnyEmptyCell {
+			variants.push("any-empty-cell");
+		}
+		variants
+	}
+
+	/// The rules with the variants named as by `variants` in play.
+	pub fn from_variants<'a, I: IntoIterator<Item=&'a str>>(variants: I) -> Result<Rules, String> {
+		let mut rules = Rules::default();
+		for variant in variants {
+			match variant {
+				"drawn-boards-count-for-both" => rules.drawn_boards_count_for_both = true,
+				"majority-tiebreak" => rules.majority_tiebreak = true,
+				"any-empty-cell" => rules.free_move = FreeMove::AnyEmptyCell,
+				_ => return Err(format!("unknown rule variant {}", variant)),
+			}
+		}
+		Ok(rules)
+	}
+}
+
+/// An Ultimate TicTacToe position.
+///
+/// Moves are `(board, cell)` pairs, where both indices count from 0 to 8 left to right and top
+/// to bottom. Each small board is stored as a 9-bit mask per player, with bit `i` for cell `i`.
 #[derive(Clone)]
 pub struct TicTacToe {
-	board: [[Option<Player>; 9]; 9],
-	winners: [Option<Player>; 9],
+	/// The cells each player has taken, indexed by `Player::index` and then by board.
+	cells: [[u16; 9]; 2],
+	/// The small boards each player has won.
+	won: [u16; 2],
+	/// The small boards that filled up without a winner.
+	drawn: u16,
 	move_restriction: Option<usize>,
 	player: Player,
-	game_over: bool,
+	result: Option<GameResult<Player>>,
+	rules: Rules,
+	/// The Zobrist hash of the cells, the winners of the small boards, the move restriction and
+	/// the player to move, kept up to date as moves are made.
+	hash: u64,
 }
 
-fn check_line(a: Option<Player>, b: Option<Player>, c: Option<Player>) -> Option<Player> {
-	match (a, b, c) {
-		(Some(a2), Some(b2), Some(c2)) if a2 == b2 && a2 == c2 => a,
-		_ => None
+/// Every cell of a small board, or every board of the big one.
+const ALL: u16 = 0x1ff;
+
+/// The rows, columns and diagonals of a 3x3 board.
+const LINES: [u16; 8] = [0x007, 0x038, 0x1c0, 0x049, 0x092, 0x124, 0x111, 0x054];
+
+/// Whether each of the 512 possible sets of cells contains a line.
+static HAS_LINE: [bool; 512] = line_table();
+
+const fn line_table() -> [bool; 512] {
+	let mut table = [false; 512];
+
+	let mut mask = 0;
+	while mask < 512 {
+		let mut i = 0;
+		while i < LINES.len() {
+			if mask as u16 & LINES[i] == LINES[i] {
+				table[mask] = true;
+			}
+			i += 1;
+		}
+		mask += 1;
 	}
+
+	table
 }
 
-fn check_for_winner(board: [Option<Player>; 9]) -> Option<Player> {
-	for i in 0..3 {
-		// columns
-		if let Some(player) = check_line(board[i], board[i+3], board[i+6]) {
-			return Some(player)
+fn has_line(mask: u16) -> bool {
+	HAS_LINE[mask as usize]
+}
 
-		// rows
-		} else if let Some(player) = check_line(board[3*i], board[3*i + 1], board[3*i + 2]) {
-			return Some(player)
-		}
+/// Random keys for Zobrist hashing: one for each player in each of the 81 cells, one for each
+/// move restriction (the last for none), one for O to move, then one for each player winning each
+/// board and one for each board being drawn.
+///
+/// The winners are hashed as well as the cells because with `FreeMove::AnyEmptyCell` a board
+/// can end up with a line for both players, and which of them won it depends on the move order.
+static ZOBRIST: [u64; ZOBRIST_KEYS] = zobrist_keys();
+
+const ZOBRIST_KEYS: usize = 2 * 81 + 10 + 1 + 2 * 9 + 9;
+
+const fn zobrist_keys() -> [u64; ZOBRIST_KEYS] {
+	let mut keys = [0; ZOBRIST_KEYS];
+
+	// splitmix64, so that the keys are the same in every build
+	let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+	let mut i = 0;
+	while i < keys.len() {
+		state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		keys[i] = z ^ (z >> 31);
+		i += 1;
 	}
 
-	// diagonals
-	if let Some(player) = check_line(board[0], board[4], board[8]) {
-		return Some(player)
-	} else if let Some(player) = check_line(board[2], board[4], board[6]) {
-		return Some(player)
+	keys
+}
+
+fn cell_key(player: Player, board: usize, cell: usize) -> u64 {
+	ZOBRIST[player.index() * 81 + board * 9 + cell]
+}
+
+fn restriction_key(restriction: Option<usize>) -> u64 {
+	ZOBRIST[2 * 81 + restriction.unwrap_or(9)]
+}
+
+fn player_key(player: Player) -> u64 {
+	match player {
+		Player::X => 0,
+		Player::O => ZOBRIST[2 * 81 + 10],
 	}
+}
+
+fn won_key(player: Player, board: usize) -> u64 {
+	ZOBRIST[2 * 81 + 11 + player.index() * 9 + board]
+}
+
+fn drawn_key(board: usize) -> u64 {
+	ZOBRIST[2 * 81 + 11 + 2 * 9 + board]
+}
 
-	None
+/// Formats a move as two digits, the board followed by the cell (e.g. `40`).
+pub fn format_move(mv: &(usize, usize)) -> String {
+	format!("{}{}", mv.0, mv.1)
 }
 
-fn line(ctx: &Context, x1: f64, y1: f64, x2: f64, y2: f64) {
-	ctx.move_to(x1, y1);
-	ctx.line_to(x2, y2);
-	ctx.stroke();
+/// Parses a move written by `format_move`.
+pub fn parse_move(s: &str) -> Option<(usize, usize)> {
+	let mut digits = s.chars().map(|c| c.to_digit(10));
+
+	match (digits.next(), digits.next(), digits.next()) {
+		(Some(Some(a)), Some(Some(b)), None) if a < 9 && b < 9 => Some((a as usize, b as usize)),
+		_ => None,
+	}
+}
+
+/// The legal moves of a position, found without allocating. Moves come in order of board, then
+/// cell.
+pub struct Moves<'a> {
+	game: &'a TicTacToe,
+	boards: u16,
+	board: usize,
+	cells: u16,
+}
+
+impl<'a> Iterator for Moves<'a> {
+	type Item = (usize, usize);
+
+	fn next(&mut self) -> Option<(usize, usize)> {
+		while self.cells == 0 {
+			if self.boards == 0 {
+				return None;
+			}
+
+			self.board = self.boards.trailing_zeros() as usize;
+			self.boards &= self.boards - 1;
+			self.cells = self.game.empty_cells(self.board);
+		}
+
+		let cell = self.cells.trailing_zeros() as usize;
+		self.cells &= self.cells - 1;
+		Some((self.board, cell))
+	}
 }
 
-fn draw_board(ctx: &Context, x1: f64, y1: f64, size: f64) {
-	ctx.save();
-	ctx.translate(x1, y1);
+/// Counts the positions reached after exactly `depth` more moves from `game`. Games that end
+/// sooner don't count.
+pub fn perft(game: &TicTacToe, depth: usize) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
 
-	line(ctx, size/3.0,     0.0,          size/3.0,     size);
-	line(ctx, 2.0*size/3.0, 0.0,          2.0*size/3.0, size);
-	line(ctx, 0.0,          size/3.0,     size,         size/3.0);
-	line(ctx, 0.0,          2.0*size/3.0, size,         2.0*size/3.0);
+	if depth == 1 {
+		return game.moves().count() as u64;
+	}
 
-	ctx.restore();
+	game.moves()
+		.map(|mv| {
+			let mut next = game.clone();
+			next.make_move_mut(&mv);
+			perft(&next, depth - 1)
+		})
+		.sum()
 }
 
 impl TicTacToe {
+	/// Starts a game with the standard rules.
 	pub fn new() -> TicTacToe {
-		TicTacToe {
-			board: [[None; 9]; 9],
-			winners: [None; 9],
+		TicTacToe::with_rules(Rules::default())
+	}
+
+	pub fn with_rules(rules: Rules) -> TicTacToe {
+		let mut game = TicTacToe {
+			cells: [[0; 9]; 2],
+			won: [0; 2],
+			drawn: 0,
 			move_restriction: None,
 			player: Player::X,
-			game_over: false
+			result: None,
+			rules,
+			hash: 0,
+		};
+		game.hash = game.zobrist();
+		game
+	}
+
+	pub fn rules(&self) -> Rules {
+		self.rules
+	}
+
+	/// Who has taken a cell, if anyone.
+	pub fn cell(&self, board: usize, cell: usize) -> Option<Player> {
+		if self.cells[0][board] & (1 << cell) != 0 {
+			Some(Player::X)
+		} else if self.cells[1][board] & (1 << cell) != 0 {
+			Some(Player::O)
+		} else {
+			None
 		}
 	}
 
-	pub fn draw(&self, ctx: &Context, w: f64, h: f64) {
-		let size = w.min(h) * 0.95;
-		ctx.translate(w/2.0 - size/2.0, h/2.0 - size/2.0);
+	/// Who has won a small board, if anyone.
+	pub fn board_winner(&self, board: usize) -> Option<Player> {
+		if self.won[0] & (1 << board) != 0 {
+			Some(Player::X)
+		} else if self.won[1] & (1 << board) != 0 {
+			Some(Player::O)
+		} else {
+			None
+		}
+	}
 
-		if !self.game_over {
-			ctx.set_source_rgb(1.0, 1.0, 0.5);
-			if let Some(i) = self.move_restriction {
-				ctx.rectangle(size / 3.0 * (i % 3) as f64, size / 3.0 * (i / 3) as f64, size / 3.0, size / 3.0);
-				ctx.fill();
-			} else {
-				ctx.rectangle(0.0, 0.0, size, size);
-				ctx.fill();
-			}
+	/// Whether a small board filled up without a winner.
+	pub fn board_drawn(&self, board: usize) -> bool {
+		self.drawn & (1 << board) != 0
+	}
+
+	/// Iterates over the legal moves without allocating.
+	pub fn moves(&self) -> Moves<'_> {
+		Moves {
+			game: self,
+			boards: self.playable_boards(),
+			board: 0,
+			cells: 0,
 		}
+	}
 
-		ctx.set_source_rgb(0.0, 0.0, 0.0);
-		ctx.set_line_width(6.0);
-		draw_board(ctx, 0.0, 0.0, size);
-
-		ctx.set_line_width(2.0);
-
-		let board_size = size/3.0;
-		let cell_size = size/9.0;
-		for (index_a, board) in self.board.iter().enumerate() {
-			let board_x = board_size * (index_a as f64 % 3.0).floor();
-			let board_y = board_size * (index_a as f64 / 3.0).floor();
-			ctx.save();
-			ctx.translate(board_x, board_y);
-
-			ctx.set_source_rgb(0.0, 0.0, 0.0);
-			draw_board(ctx, 0.0, 0.0, size/3.0);
-
-			for (index_b, cell) in board.iter().enumerate() {
-				let cell_x = cell_size * (index_b as f64 % 3.0).floor();
-				let cell_y = cell_size * (index_b as f64 / 3.0).floor();
-				ctx.save();
-				ctx.translate(cell_x + cell_size/2.0, cell_y + cell_size/2.0);
-
-				match cell {
-					&Some(Player::X) => {
-						let off = cell_size/2.0 * 0.8;
-						ctx.set_source_rgb(1.0, 0.0, 0.0);
-						line(ctx, -off, -off, off, off);
-						line(ctx, off, -off, -off, off);
-					},
-					&Some(Player::O) => {
-						ctx.set_source_rgb(0.0, 0.0, 1.0);
-						ctx.arc(0.0, 0.0, cell_size/2.0 * 0.8, 0.0, 2.0*PI);
-						ctx.stroke();
-					},
-					&None => { /* empty cell */ }
-				}
+	fn empty_cells(&self, board: usize) -> u16 {
+		ALL & !(self.cells[0][board] | self.cells[1][board])
+	}
 
-				ctx.restore();
-			}
+	/// The boards that can still be played in, i.e. that nobody has won and that aren't full.
+	fn open_boards(&self) -> u16 {
+		ALL & !(self.won[0] | self.won[1] | self.drawn)
+	}
 
-			ctx.restore();
+	/// The boards the current player may move in.
+	fn playable_boards(&self) -> u16 {
+		if self.result.is_some() {
+			// no possible moves once the game is over
+			return 0;
 		}
 
-		for (index_a, winner) in self.winners.iter().enumerate() {
-			if let &Some(player) = winner {
-				ctx.save();
-				ctx.translate((index_a as f64 % 3.0).floor() * board_size + board_size/2.0, (index_a as f64 / 3.0).floor() * board_size + board_size/2.0);
-				ctx.set_line_width(6.0);
-
-				match player {
-					Player::X => {
-						let off = board_size/2.0 * 0.8;
-						ctx.set_source_rgb(1.0, 0.0, 0.0);
-						line(ctx, -off, -off, off, off);
-						line(ctx, off, -off, -off, off);
-					}
-					Player::O => {
-						ctx.set_source_rgb(0.0, 0.0, 1.0);
-						ctx.arc(0.0, 0.0, board_size/2.0 * 0.8, 0.0, 2.0*PI);
-						ctx.stroke();
+		match (self.move_restriction, self.rules.free_move) {
+			(Some(board), _) => 1 << board,
+			(None, FreeMove::AnyOpenBoard) => self.open_boards(),
+			(None, FreeMove::AnyEmptyCell) => (0..9)
+				.filter(|&board| self.empty_cells(board) != 0)
+				.fold(0, |boards, board| boards | 1 << board),
+		}
+	}
+
+	/// Works out the Zobrist hash from scratch. Positions under the same rules get the same hash
+	/// however they were reached.
+	fn zobrist(&self) -> u64 {
+		let mut hash = restriction_key(self.move_restriction) ^ player_key(self.player);
+
+		for &player in &[Player::X, Player::O] {
+			for board in 0..9 {
+				for cell in 0..9 {
+					if self.cells[player.index()][board] & (1 << cell) != 0 {
+						hash ^= cell_key(player, board, cell);
 					}
 				}
 
-				ctx.restore();
+				if self.board_winner(board) == Some(player) {
+					hash ^= won_key(player, board);
+				}
+			}
+		}
+
+		for board in 0..9 {
+			if self.board_drawn(board) {
+				hash ^= drawn_key(board);
 			}
 		}
-	}
 
-	pub fn click(&mut self, w: f64, h: f64, x: f64, y: f64) -> Option<(usize, usize)> {
-		let board_size = w.min(h) * 0.95;
-		let cell_size = board_size / 9.0;
+		hash
+	}
 
-		let dx = w/2.0 - board_size/2.0;
-		let dy = h/2.0 - board_size/2.0;
+	/// Works out whether the game is over, assuming `self.player` has just moved.
+	fn check_result(&self) -> Option<GameResult<Player>> {
+		// a drawn board can complete a line for either player, so the player who just moved gets
+		// the first chance
+		for &player in &[self.player, self.player.other()] {
+			let mut boards = self.won[player.index()];
+			if self.rules.drawn_boards_count_for_both {
+				boards |= self.drawn;
+			}
 
-		let cell_x = ((x - dx) / cell_size).floor();
-		let cell_y = ((y - dy) / cell_size).floor();
+			if has_line(boards) {
+				return Some(GameResult::Win(player));
+			}
+		}
 
-		let index_a = (cell_x / 3.0).floor() + 3.0 * (cell_y / 3.0).floor();
-		let index_b = (cell_x % 3.0).floor() + 3.0 * (cell_y % 3.0).floor();
+		if self.open_boards() != 0 {
+			return None;
+		}
 
-		let mv = (index_a as usize, index_b as usize);
+		// every board is decided without a line of them
+		let x_boards = self.won[0].count_ones();
+		let o_boards = self.won[1].count_ones();
 
-		if self.make_move_mut(&mv) {
-			Some(mv)
+		if self.rules.majority_tiebreak && x_boards != o_boards {
+			Some(GameResult::Win(if x_boards > o_boards { Player::X } else { Player::O }))
 		} else {
-			None
+			Some(GameResult::Draw)
 		}
 	}
 }
 
+impl Default for TicTacToe {
+	fn default() -> TicTacToe {
+		TicTacToe::new()
+	}
+}
+
 impl Game for TicTacToe {
 	type Move = (usize, usize);
 	type Player = Player;
 
 	fn available_moves(&self) -> Vec<Self::Move> {
-		let mut moves = vec!();
-
-		if self.game_over {
-			// no possible moves if someone has already won
-			return moves;
-		}
-
-		if let Some(index_a) = self.move_restriction {
-			for (index_b, cell) in self.board[index_a].iter().enumerate() {
-				if cell.is_none() {
-					moves.push((index_a, index_b))
-				}
-			}
-		} else {
-			for (index_a, board) in self.board.iter().enumerate() {
-				if self.winners[index_a] == None {
-					for (index_b, cell) in board.iter().enumerate() {
-						if cell.is_none() {
-							moves.push((index_a, index_b))
-						}
-					}
-				}
-			}
-		}
-
-		moves
+		self.moves().collect()
 	}
 
 	fn make_move_mut(&mut self, m: &Self::Move) -> bool {
 		let &(index_a, index_b) = m;
 
-		// can't move if someone has already won
-		if self.game_over {
+		// make sure the game isn't over and the move conforms to the move restrictions
+		if index_a >= 9 || index_b >= 9 || self.playable_boards() & (1 << index_a) == 0 {
 			return false;
 		}
 
-		// make sure the move conforms to the move restrictions
-		if let Some(r) = self.move_restriction {
-			if index_a != r { return false };
-		}
-
-		// make sure that there isn't already a winner for that board
-		if self.winners[index_a].is_some() {
+		// make sure the cell is empty
+		if self.empty_cells(index_a) & (1 << index_b) == 0 {
 			return false;
 		}
 
-		// make sure the cell is empty
-		if self.board[index_a][index_b].is_none() {
-			self.board[index_a][index_b] = Some(self.player);
-
-			if let Some(winner) = check_for_winner(self.board[index_a]) {
-				self.winners[index_a] = Some(winner)
-			}
-
-			// set the move restriction
-			if self.winners[index_b].is_none() {
-				// there is a restriction of no one has won the corresponding cell yet
-				self.move_restriction = Some(index_b)
-			} else {
-				// if someone has won the cell, there is no restriction
-				self.move_restriction = None
-			}
-
-			if let Some(player) = check_for_winner(self.winners) {
-				self.game_over = true;
-				self.player = player;
-			} else {
-				// toggle player
-				self.player = match self.player {
-					Player::X => Player::O,
-					Player::O => Player::X
-				};
+		let player = self.player.index();
+		self.cells[player][index_a] |= 1 << index_b;
+		self.hash ^= cell_key(self.player, index_a, index_b) ^ restriction_key(self.move_restriction) ^ player_key(self.player);
+
+		// moves into a board that is already won don't change its winner
+		if self.board_winner(index_a).is_none() {
+			if has_line(self.cells[player][index_a]) {
+				self.won[player] |= 1 << index_a;
+				self.hash ^= won_key(self.player, index_a);
+			} else if self.empty_cells(index_a) == 0 {
+				self.drawn |= 1 << index_a;
+				self.hash ^= drawn_key(index_a);
 			}
+		}
 
-			true
+		// there is a restriction if the corresponding board can still be played in
+		self.move_restriction = if self.open_boards() & (1 << index_b) != 0 {
+			Some(index_b)
 		} else {
-			false
+			None
+		};
+
+		self.result = self.check_result();
+		match self.result {
+			Some(GameResult::Win(player)) => self.player = player,
+			_ => self.player = self.player.other(),
 		}
+		self.hash ^= restriction_key(self.move_restriction) ^ player_key(self.player);
+
+		true
 	}
 
 	fn make_move(&self, m: &Self::Move) -> Option<Box<Self>> {
@@ -280,13 +475,50 @@ impl Game for TicTacToe {
 	}
 
 	fn get_winner(&self) -> Option<Player> {
-		if self.game_over {
-			Some(self.player)
-		} else {
-			None
+		match self.result {
+			Some(GameResult::Win(player)) => Some(player),
+			_ => None,
 		}
 	}
 
+	fn get_result(&self) -> Option<GameResult<Player>> {
+		self.result
+	}
+
+	fn moves_left(&self) -> Option<usize> {
+		if self.result.is_some() {
+			return Some(0);
+		}
+
+		// only the open boards can be played in, unless free moves can go in any empty cell
+		let boards = match self.rules.free_move {
+			FreeMove::AnyOpenBoard => self.open_boards(),
+			FreeMove::AnyEmptyCell => ALL,
+		};
+		Some((0..9)
+			.filter(|&board| boards & (1 << board) != 0)
+			.map(|board| self.empty_cells(board).count_ones() as usize)
+			.sum())
+	}
+
+	fn hash_key(&self) -> Option<u64> {
+		Some(self.hash)
+	}
+
+	fn random_move<R: Rng>(&self, rand: &mut R) -> Option<Self::Move> {
+		let boards = self.playable_boards();
+		let count: usize = (0..9)
+			.filter(|&board| boards & (1 << board) != 0)
+			.map(|board| self.empty_cells(board).count_ones() as usize)
+			.sum();
+
+		if count == 0 {
+			return None;
+		}
+
+		// same choice as picking from `available_moves`, without building the list
+		self.moves().nth(rand.gen_range(0, count))
+	}
 
 	fn to_str(&self) -> String {
 		let mut st = String::new();
@@ -295,26 +527,31 @@ impl Game for TicTacToe {
 			if x % 3 == 0 && x != 0 {
 				st.push_str("-------+-------+-------\n");
 			}
-			st.push_str(" ");
+			st.push(' ');
 
 			for y in 0..9 {
 				if y % 3 == 0 && y != 0 {
 					st.push_str("| ");
 				}
 
-				let index_a = (y % 3) + 3*(x % 3);
-				let index_b = (y / 3) + 3*(x / 3);
+				let index_a = (y / 3) + 3*(x / 3);
+				let index_b = (y % 3) + 3*(x % 3);
 
-				st.push_str(match self.board[index_a][index_b] {
+				st.push_str(match self.cell(index_a, index_b) {
 					Some(Player::X) => "X ",
 					Some(Player::O) => "O ",
 					None => "  ",
 				});
 			}
 
-			st.push_str("\n");
+			st.push('\n');
 		}
 
 		st
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod reference;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,164 @@
+use super::{ has_line, Player, Rules, TicTacToe };
+
+/// The notation of the starting position.
+pub const START_NOTATION: &str = "9/9/9/9/9/9/9/9/9 9 - X";
+
+fn player_char(player: Player) -> char {
+	match player {
+		Player::X => 'X',
+		Player::O => 'O',
+	}
+}
+
+/// Writes nine cells, with each run of empty cells as a single digit.
+fn write_cells(out: &mut String, cells: [Option<Player>; 9]) {
+	let mut empty = 0;
+
+	for cell in &cells {
+		match *cell {
+			Some(player) => {
+				if empty > 0 {
+					out.push_str(&empty.to_string());
+					empty = 0;
+				}
+				out.push(player_char(player));
+			},
+			None => empty += 1,
+		}
+	}
+
+	if empty > 0 {
+		out.push_str(&empty.to_string());
+	}
+}
+
+/// Reads nine cells written by `write_cells`.
+fn read_cells(s: &str) -> Result<[Option<Player>; 9], String> {
+	let mut cells = [None; 9];
+	let mut i = 0;
+
+	for c in s.chars() {
+		let (player, count) = match c {
+			'X' => (Some(Player::X), 1),
+			'O' => (Some(Player::O), 1),
+			'1'..='9' => (None, c as usize - '0' as usize),
+			_ => return Err(format!("unexpected `{}` in `{}`", c, s)),
+		};
+
+		if i + count > 9 {
+			return Err(format!("`{}` has more than nine cells", s));
+		}
+
+		for cell in &mut cells[i..i + count] {
+			*cell = player;
+		}
+		i += count;
+	}
+
+	if i < 9 {
+		Err(format!("`{}` has fewer than nine cells", s))
+	} else {
+		Ok(cells)
+	}
+}
+
+impl TicTacToe {
+	/// Writes the position as four fields separated by spaces: the cells of each small board
+	/// separated by `/`, the winners of the small boards, the board the next move has to be in
+	/// (`-` for a free move) and the player to move. Runs of empty cells or undecided boards are
+	/// written as a digit, so the starting position is `9/9/9/9/9/9/9/9/9 9 - X`.
+	///
+	/// The rules in play aren't part of the notation.
+	pub fn to_notation(&self) -> String {
+		let mut out = String::new();
+
+		for board in 0..9 {
+			if board > 0 {
+				out.push('/');
+			}
+
+			let mut cells = [None; 9];
+			for (cell, c) in cells.iter_mut().enumerate() {
+				*c = self.cell(board, cell);
+			}
+			write_cells(&mut out, cells);
+		}
+
+		out.push(' ');
+		let mut winners = [None; 9];
+		for (board, winner) in winners.iter_mut().enumerate() {
+			*winner = self.board_winner(board);
+		}
+		write_cells(&mut out, winners);
+
+		out.push(' ');
+		match self.move_restriction {
+			Some(board) => out.push_str(&board.to_string()),
+			None => out.push('-'),
+		}
+
+		out.push(' ');
+		out.push(player_char(self.player));
+
+		out
+	}
+
+	/// Reads a position written by `to_notation`, to be played with `rules`.
+	pub fn from_notation(notation: &str, rules: Rules) -> Result<TicTacToe, String> {
+		let fields: Vec<&str> = notation.split_whitespace().collect();
+		let (boards, winners, restriction, player) = match fields.as_slice() {
+			&[boards, winners, restriction, player] => (boards, winners, restriction, player),
+			_ => return Err("a position has four fields: cells, winners, restriction and player".to_owned()),
+		};
+
+		let mut game = TicTacToe::with_rules(rules);
+
+		let boards: Vec<&str> = boards.split('/').collect();
+		if boards.len() != 9 {
+			return Err(format!("expected nine boards, found {}", boards.len()));
+		}
+
+		for (board, cells) in boards.iter().enumerate() {
+			for (cell, c) in read_cells(cells)?.iter().enumerate() {
+				if let Some(player) = *c {
+					game.cells[player.index()][board] |= 1 << cell;
+				}
+			}
+		}
+
+		for (board, winner) in read_cells(winners)?.iter().enumerate() {
+			let lines = (has_line(game.cells[0][board]), has_line(game.cells[1][board]));
+
+			match (*winner, lines) {
+				(Some(player), _) if has_line(game.cells[player.index()][board]) => game.won[player.index()] |= 1 << board,
+				(Some(player), _) => return Err(format!("{:?} won board {} without a line", player, board)),
+				(None, (false, false)) => if game.empty_cells(board) == 0 {
+					game.drawn |= 1 << board;
+				},
+				(None, _) => return Err(format!("board {} has a line but no winner", board)),
+			}
+		}
+
+		game.move_restriction = match restriction {
+			"-" => None,
+			_ => match restriction.parse::<usize>() {
+				Ok(board) if board < 9 && game.open_boards() & (1 << board) != 0 => Some(board),
+				_ => return Err(format!("{} is not a board that can be played in", restriction)),
+			},
+		};
+
+		let player = match player {
+			"X" => Player::X,
+			"O" => Player::O,
+			_ => return Err(format!("expected X or O to move, found {}", player)),
+		};
+
+		// the result is worked out as if the other player has just moved
+		game.player = player.other();
+		game.result = game.check_result();
+		game.player = player;
+		game.hash = game.zobrist();
+
+		Ok(game)
+	}
+}
@@ -0,0 +1,127 @@
+use ai::Game;
+use super::{ format_move, parse_move, Rules, TicTacToe };
+use super::notation::START_NOTATION;
+
+/// A game written down as the position it started from and the moves played since.
+///
+/// As text, a record has one field per line:
+///
+/// ```text
+/// position startpos
+/// rules drawn-boards-count-for-both
+/// moves 40 02 21
+/// ```
+///
+/// `position` is `startpos` or a position in the format of `TicTacToe::to_notation`, and `rules`
+/// lists the rule variants in play, if any. Blank lines and lines starting with `#` are ignored.
+#[derive(Clone)]
+pub struct GameRecord {
+	start: TicTacToe,
+	moves: Vec<(usize, usize)>,
+}
+
+impl GameRecord {
+	pub fn new(start: TicTacToe) -> GameRecord {
+		GameRecord {
+			start,
+			moves: Vec::new(),
+		}
+	}
+
+	pub fn start(&self) -> &TicTacToe {
+		&self.start
+	}
+
+	pub fn moves(&self) -> &[(usize, usize)] {
+		&self.moves
+	}
+
+	/// The position after every recorded move.
+	pub fn position(&self) -> TicTacToe {
+		self.position_after(self.moves.len())
+	}
+
+	/// The position after the first `moves` recorded moves.
+	pub fn position_after(&self, moves: usize) -> TicTacToe {
+		let mut game = self.start.clone();
+		for mv in &self.moves[..moves] {
+			game.make_move_mut(mv);
+		}
+		game
+	}
+
+	/// Records a move, returning `false` if it is illegal in the current position.
+	pub fn push(&mut self, mv: (usize, usize)) -> bool {
+		if self.position().make_move_mut(&mv) {
+			self.moves.push(mv);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Takes back the last move.
+	pub fn pop(&mut self) -> Option<(usize, usize)> {
+		self.moves.pop()
+	}
+
+	pub fn to_text(&self) -> String {
+		let notation = self.start.to_notation();
+		let mut text = if notation == START_NOTATION {
+			"position startpos\n".to_owned()
+		} else {
+			format!("position {}\n", notation)
+		};
+
+		let variants = self.start.rules().variants();
+		if !variants.is_empty() {
+			text.push_str(&format!("rules {}\n", variants.join(" ")));
+		}
+
+		let moves: Vec<String> = self.moves.iter().map(format_move).collect();
+		text.push_str(&format!("moves {}\n", moves.join(" ")));
+
+		text
+	}
+
+	/// Reads a record written by `to_text`, checking that every move is legal.
+	pub fn from_text(text: &str) -> Result<GameRecord, String> {
+		let mut position = None;
+		let mut rules = Rules::default();
+		let mut moves = Vec::new();
+
+		for line in text.lines().map(str::trim) {
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let (key, value) = match line.find(' ') {
+				Some(i) => (&line[..i], line[i + 1..].trim()),
+				None => (line, ""),
+			};
+
+			match key {
+				"position" => position = Some(value.to_owned()),
+				"rules" => rules = Rules::from_variants(value.split_whitespace())?,
+				"moves" => for mv in value.split_whitespace() {
+					moves.push(parse_move(mv).ok_or_else(|| format!("{} is not a move", mv))?);
+				},
+				_ => return Err(format!("unknown record field {}", key)),
+			}
+		}
+
+		let start = match position.as_deref() {
+			None | Some("startpos") => TicTacToe::with_rules(rules),
+			Some(notation) => TicTacToe::from_notation(notation, rules)?,
+		};
+
+		let mut record = GameRecord::new(start);
+		for mv in moves {
+			if !record.push(mv) {
+				return Err(format!("illegal move {} after {} moves", format_move(&mv), record.moves.len()));
+			}
+		}
+
+		Ok(record)
+	}
+}
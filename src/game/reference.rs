@@ -0,0 +1,222 @@
+//! The original array-based implementation of the rules, kept to check the bitboard
+//! implementation against.
+
+use ai::{ Game, GameResult };
+use super::{ FreeMove, Player, Rules };
+
+#[derive(Clone)]
+pub struct Reference {
+	board: [[Option<Player>; 9]; 9],
+	winners: [Option<Player>; 9],
+	move_restriction: Option<usize>,
+	player: Player,
+	result: Option<GameResult<Player>>,
+	rules: Rules,
+}
+
+fn check_line(a: Option<Player>, b: Option<Player>, c: Option<Player>) -> Option<Player> {
+	match (a, b, c) {
+		(Some(a2), Some(b2), Some(c2)) if a2 == b2 && a2 == c2 => a,
+		_ => None
+	}
+}
+
+fn check_for_winner(board: [Option<Player>; 9]) -> Option<Player> {
+	for i in 0..3 {
+		// columns
+		if let Some(player) = check_line(board[i], board[i+3], board[i+6]) {
+			return Some(player)
+
+		// rows
+		} else if let Some(player) = check_line(board[3*i], board[3*i + 1], board[3*i + 2]) {
+			return Some(player)
+		}
+	}
+
+	// diagonals
+	if let Some(player) = check_line(board[0], board[4], board[8]) {
+		return Some(player)
+	} else if let Some(player) = check_line(board[2], board[4], board[6]) {
+		return Some(player)
+	}
+
+	None
+}
+
+impl Reference {
+	pub fn with_rules(rules: Rules) -> Reference {
+		Reference {
+			board: [[None; 9]; 9],
+			winners: [None; 9],
+			move_restriction: None,
+			player: Player::X,
+			result: None,
+			rules,
+		}
+	}
+
+	fn board_open(&self, index: usize) -> bool {
+		self.winners[index].is_none() && self.board[index].iter().any(Option::is_none)
+	}
+
+	fn board_drawn(&self, index: usize) -> bool {
+		self.winners[index].is_none() && self.board[index].iter().all(Option::is_some)
+	}
+
+	fn check_result(&self) -> Option<GameResult<Player>> {
+		for &player in &[self.player, self.player.other()] {
+			let mut boards = [None; 9];
+			for (index, board) in boards.iter_mut().enumerate() {
+				if self.winners[index] == Some(player) || (self.rules.drawn_boards_count_for_both && self.board_drawn(index)) {
+					*board = Some(player);
+				}
+			}
+
+			if check_for_winner(boards).is_some() {
+				return Some(GameResult::Win(player));
+			}
+		}
+
+		if (0..9).any(|i| self.board_open(i)) {
+			return None;
+		}
+
+		let x_boards = self.winners.iter().filter(|&&w| w == Some(Player::X)).count();
+		let o_boards = self.winners.iter().filter(|&&w| w == Some(Player::O)).count();
+
+		if self.rules.majority_tiebreak && x_boards != o_boards {
+			Some(GameResult::Win(if x_boards > o_boards { Player::X } else { Player::O }))
+		} else {
+			Some(GameResult::Draw)
+		}
+	}
+}
+
+impl Game for Reference {
+	type Move = (usize, usize);
+	type Player = Player;
+
+	fn available_moves(&self) -> Vec<Self::Move> {
+		let mut moves = vec!();
+
+		if self.result.is_some() {
+			return moves;
+		}
+
+		if let Some(index_a) = self.move_restriction {
+			for (index_b, cell) in self.board[index_a].iter().enumerate() {
+				if cell.is_none() {
+					moves.push((index_a, index_b))
+				}
+			}
+		} else {
+			let any_board = self.rules.free_move == FreeMove::AnyEmptyCell;
+
+			for (index_a, board) in self.board.iter().enumerate() {
+				if any_board || self.board_open(index_a) {
+					for (index_b, cell) in board.iter().enumerate() {
+						if cell.is_none() {
+							moves.push((index_a, index_b))
+						}
+					}
+				}
+			}
+		}
+
+		moves
+	}
+
+	fn make_move_mut(&mut self, m: &Self::Move) -> bool {
+		let &(index_a, index_b) = m;
+
+		if self.result.is_some() {
+			return false;
+		}
+
+		if let Some(r) = self.move_restriction {
+			if index_a != r { return false };
+		}
+
+		if self.winners[index_a].is_some() && self.rules.free_move == FreeMove::AnyOpenBoard {
+			return false;
+		}
+
+		if self.board[index_a][index_b].is_none() {
+			self.board[index_a][index_b] = Some(self.player);
+
+			if self.winners[index_a].is_none() {
+				self.winners[index_a] = check_for_winner(self.board[index_a]);
+			}
+
+			if self.board_open(index_b) {
+				self.move_restriction = Some(index_b)
+			} else {
+				self.move_restriction = None
+			}
+
+			self.result = self.check_result();
+			match self.result {
+				Some(GameResult::Win(player)) => self.player = player,
+				_ => self.player = self.player.other(),
+			}
+
+			true
+		} else {
+			false
+		}
+	}
+
+	fn make_move(&self, m: &Self::Move) -> Option<Box<Self>> {
+		let mut c = self.clone();
+		if c.make_move_mut(m) {
+			Some(Box::new(c))
+		} else {
+			None
+		}
+	}
+
+	fn get_cur_player(&self) -> Player {
+		self.player
+	}
+
+	fn get_winner(&self) -> Option<Player> {
+		match self.result {
+			Some(GameResult::Win(player)) => Some(player),
+			_ => None,
+		}
+	}
+
+	fn get_result(&self) -> Option<GameResult<Player>> {
+		self.result
+	}
+
+	fn to_str(&self) -> String {
+		let mut st = String::new();
+
+		for x in 0..9 {
+			if x % 3 == 0 && x != 0 {
+				st.push_str("-------+-------+-------\n");
+			}
+			st.push(' ');
+
+			for y in 0..9 {
+				if y % 3 == 0 && y != 0 {
+					st.push_str("| ");
+				}
+
+				let index_a = (y / 3) + 3*(x / 3);
+				let index_b = (y % 3) + 3*(x % 3);
+
+				st.push_str(match self.board[index_a][index_b] {
+					Some(Player::X) => "X ",
+					Some(Player::O) => "O ",
+					None => "  ",
+				});
+			}
+
+			st.push('\n');
+		}
+
+		st
+	}
+}
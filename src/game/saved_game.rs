@@ -0,0 +1,142 @@
+use ai::Game;
+use super::{ format_move, GameRecord, Player, TicTacToe };
+
+/// What the AI's search thought of the position when a move was played.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MoveStats {
+	pub total_sims: u64,
+	pub confidence: f64,
+}
+
+/// A game saved from the viewer: a `GameRecord` followed by the sides the AI played and the
+/// search statistics for each move, e.g.
+///
+/// ```text
+/// position startpos
+/// moves 40 04 48
+/// ai O
+/// sims 1520 98304 2210
+/// confidence 0.512 0.538 0.476
+/// ```
+///
+/// Moves made before the AI reported anything have `-` in place of their statistics.
+pub struct SavedGame {
+	pub record: GameRecord,
+	pub ai_players: Vec<Player>,
+	pub stats: Vec<Option<MoveStats>>,
+}
+
+impl SavedGame {
+	pub fn new(start: TicTacToe, ai_players: Vec<Player>) -> SavedGame {
+		SavedGame {
+			record: GameRecord::new(start),
+			ai_players,
+			stats: Vec::new(),
+		}
+	}
+
+	/// Records a move, returning `false` if it is illegal in the current position.
+	pub fn push(&mut self, mv: (usize, usize), stats: Option<MoveStats>) -> bool {
+		if self.record.push(mv) {
+			self.stats.push(stats);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Takes back the last move along with its statistics.
+	pub fn pop(&mut self) -> Option<((usize, usize), Option<MoveStats>)> {
+		self.record.pop().map(|mv| (mv, self.stats.pop().unwrap_or(None)))
+	}
+
+	pub fn to_text(&self) -> String {
+		let mut text = self.record.to_text();
+
+		let players: Vec<String> = self.ai_players.iter().map(|p| format!("{:?}", p)).collect();
+		text.push_str(&format!("ai {}\n", players.join(" ")));
+
+		let sims: Vec<String> = self.stats.iter()
+			.map(|s| s.map(|s| s.total_sims.to_string()).unwrap_or_else(|| "-".to_owned()))
+			.collect();
+		text.push_str(&format!("sims {}\n", sims.join(" ")));
+
+		let confidence: Vec<String> = self.stats.iter()
+			.map(|s| s.map(|s| format!("{:.3}", s.confidence)).unwrap_or_else(|| "-".to_owned()))
+			.collect();
+		text.push_str(&format!("confidence {}\n", confidence.join(" ")));
+
+		text
+	}
+
+	pub fn from_text(text: &str) -> Result<SavedGame, String> {
+		let mut record = String::new();
+		let mut ai_players = Vec::new();
+		let mut sims = Vec::new();
+		let mut confidence = Vec::new();
+
+		for line in text.lines() {
+			let mut words = line.split_whitespace();
+
+			match words.next() {
+				Some("ai") => for word in words {
+					ai_players.push(match word {
+						"X" => Player::X,
+						"O" => Player::O,
+						_ => return Err(format!("expected X or O, found {}", word)),
+					});
+				},
+				Some("sims") => for word in words {
+					sims.push(match word {
+						"-" => None,
+						_ => Some(word.parse::<u64>().map_err(|_| format!("{} is not a number of simulations", word))?),
+					});
+				},
+				Some("confidence") => for word in words {
+					confidence.push(match word {
+						"-" => None,
+						_ => Some(word.parse::<f64>().map_err(|_| format!("{} is not a confidence", word))?),
+					});
+				},
+				// everything else belongs to the game record
+				_ => {
+					record.push_str(line);
+					record.push('\n');
+				},
+			}
+		}
+
+		let record = GameRecord::from_text(&record)?;
+		let moves = record.moves().len();
+
+		// statistics are optional, but there has to be one entry per move if they are given
+		let stats = match (sims.len(), confidence.len()) {
+			(0, 0) => vec![None; moves],
+			(s, c) if s == moves && c == moves => sims.into_iter().zip(confidence)
+				.map(|stats| match stats {
+					(Some(total_sims), Some(confidence)) => Some(MoveStats { total_sims, confidence }),
+					_ => None,
+				})
+				.collect(),
+			_ => return Err(format!("expected statistics for {} moves", moves)),
+		};
+
+		Ok(SavedGame {
+			record,
+			ai_players,
+			stats,
+		})
+	}
+
+	/// Describes the `index`th move for the replay view.
+	pub fn describe_move(&self, index: usize) -> String {
+		let mv = self.record.moves()[index];
+		let player = self.record.position_after(index).get_cur_player();
+
+		match self.stats[index] {
+			Some(s) => format!("{:?} played {} ({} sims, {}% confidence)",
+				player, format_move(&mv), s.total_sims, (s.confidence*100.0).floor()),
+			None => format!("{:?} played {}", player, format_move(&mv)),
+		}
+	}
+}
@@ -0,0 +1,590 @@
+use rand::{ Rng, SeedableRng, XorShiftRng };
+
+use ai::{ Game, GameResult };
+use super::*;
+use super::reference::Reference;
+
+const EMPTY: &str = ".........";
+const WON_X: &str = "XXXOO....";
+const WON_O: &str = "OOOXX.X..";
+const DRAWN: &str = "XOXXOOOXX";
+/// Filling cell 8 with either player draws this board.
+const ALMOST_DRAWN: &str = "XOXXOOOX.";
+
+/// Builds a position from nine small boards written as nine cells each, using `X`, `O` and `.`.
+fn position(rules: Rules, boards: [&str; 9], player: Player, restriction: Option<usize>) -> TicTacToe {
+	let mut game = TicTacToe::with_rules(rules);
+
+	for (index, cells) in boards.iter().enumerate() {
+		for (cell, c) in cells.chars().enumerate() {
+			match c {
+				'X' => game.cells[0][index] |= 1 << cell,
+				'O' => game.cells[1][index] |= 1 << cell,
+				_ => {},
+			}
+		}
+
+		if has_line(game.cells[0][index]) {
+			game.won[0] |= 1 << index;
+		} else if has_line(game.cells[1][index]) {
+			game.won[1] |= 1 << index;
+		} else if game.empty_cells(index) == 0 {
+			game.drawn |= 1 << index;
+		}
+	}
+
+	game.player = player;
+	game.move_restriction = restriction;
+	game.hash = game.zobrist();
+	game
+}
+
+fn drawn_for_both() -> Rules {
+	Rules { drawn_boards_count_for_both: true, ..Rules::default() }
+}
+
+fn majority() -> Rules {
+	Rules { majority_tiebreak: true, ..Rules::default() }
+}
+
+fn any_empty_cell() -> Rules {
+	Rules { free_move: FreeMove::AnyEmptyCell, ..Rules::default() }
+}
+
+#[test]
+fn drawn_board_does_not_complete_a_line_by_default() {
+	let mut game = position(Rules::default(), [WON_X, WON_X, ALMOST_DRAWN, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(2));
+
+	assert!(game.make_move_mut(&(2, 8)));
+	assert!(game.board_drawn(2));
+	assert_eq!(game.get_result(), None);
+	assert_eq!(game.move_restriction, Some(8));
+}
+
+#[test]
+fn drawn_board_completes_a_line_when_it_counts_for_both() {
+	let mut game = position(drawn_for_both(), [WON_X, WON_X, ALMOST_DRAWN, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(2));
+
+	assert!(game.make_move_mut(&(2, 8)));
+	assert_eq!(game.get_result(), Some(GameResult::Win(Player::X)));
+	assert!(game.available_moves().is_empty());
+}
+
+#[test]
+fn drawn_board_can_complete_the_other_players_line() {
+	let mut game = position(drawn_for_both(), [WON_X, WON_X, ALMOST_DRAWN, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(2));
+
+	assert!(game.make_move_mut(&(2, 8)));
+	assert_eq!(game.get_winner(), Some(Player::X));
+}
+
+#[test]
+fn decided_boards_without_a_line_are_a_draw_by_default() {
+	let mut game = position(Rules::default(), [WON_X, WON_X, WON_O, WON_O, WON_O, WON_X, WON_X, DRAWN, ALMOST_DRAWN], Player::X, Some(8));
+
+	assert!(game.make_move_mut(&(8, 8)));
+	assert_eq!(game.get_result(), Some(GameResult::Draw));
+}
+
+#[test]
+fn majority_tiebreak_picks_the_player_with_more_boards() {
+	let mut game = position(majority(), [WON_X, WON_X, WON_O, WON_O, WON_O, WON_X, WON_X, DRAWN, ALMOST_DRAWN], Player::O, Some(8));
+
+	assert!(game.make_move_mut(&(8, 8)));
+	assert_eq!(game.get_result(), Some(GameResult::Win(Player::X)));
+}
+
+#[test]
+fn majority_tiebreak_with_equal_boards_is_a_draw() {
+	let mut game = position(majority(), [WON_X, WON_X, WON_O, WON_O, WON_O, WON_X, DRAWN, DRAWN, ALMOST_DRAWN], Player::X, Some(8));
+
+	assert!(game.make_move_mut(&(8, 8)));
+	assert_eq!(game.get_result(), Some(GameResult::Draw));
+}
+
+#[test]
+fn sent_to_won_board_moves_in_any_open_board_by_default() {
+	let mut game = position(Rules::default(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(4));
+
+	assert!(game.make_move_mut(&(4, 0)));
+	assert_eq!(game.move_restriction, None);
+
+	let moves = game.available_moves();
+	assert_eq!(moves.len(), 8 * 9 - 1);
+	assert!(moves.iter().all(|&(board, _)| board != 0));
+	assert!(!game.make_move_mut(&(0, 5)));
+}
+
+#[test]
+fn sent_to_full_board_moves_in_any_open_board() {
+	let mut game = position(Rules::default(), [DRAWN, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(4));
+
+	assert!(game.make_move_mut(&(4, 0)));
+	assert_eq!(game.move_restriction, None);
+	assert_eq!(game.available_moves().len(), 8 * 9 - 1);
+}
+
+#[test]
+fn sent_to_won_board_moves_in_any_empty_cell_with_any_empty_cell() {
+	let mut game = position(any_empty_cell(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(4));
+
+	assert!(game.make_move_mut(&(4, 0)));
+	assert_eq!(game.move_restriction, None);
+
+	let moves = game.available_moves();
+	assert_eq!(moves.len(), 4 + 8 * 9 - 1);
+	assert!(moves.contains(&(0, 5)));
+
+	// completing a line in a board that is already won doesn't change its winner
+	assert!(game.make_move_mut(&(0, 5)));
+	assert_eq!(game.board_winner(0), Some(Player::X));
+	assert_eq!(game.move_restriction, Some(5));
+}
+
+#[test]
+fn any_empty_cell_still_ends_when_every_board_is_decided() {
+	let mut game = position(any_empty_cell(), [WON_X, WON_X, WON_O, WON_O, WON_O, WON_X, WON_X, DRAWN, ALMOST_DRAWN], Player::X, Some(8));
+
+	assert!(game.make_move_mut(&(8, 8)));
+	assert_eq!(game.get_result(), Some(GameResult::Draw));
+	assert!(game.available_moves().is_empty());
+}
+
+#[test]
+fn perft_counts_from_the_empty_board() {
+	let game = TicTacToe::new();
+	let expected = [1, 81, 720, 6336, 55080, 473256];
+
+	for (depth, &count) in expected.iter().enumerate() {
+		assert_eq!(perft(&game, depth), count, "perft({})", depth);
+	}
+}
+
+#[test]
+fn perft_of_a_finished_game_has_no_moves() {
+	let mut game = position(Rules::default(), [WON_O, WON_O, "OO.XX....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(2));
+	assert_eq!(perft(&game, 1), 5);
+	assert_eq!(perft(&game, 2), 4 * 9);
+
+	game.make_move_mut(&(2, 2));
+	assert_eq!(perft(&game, 0), 1);
+	assert_eq!(perft(&game, 1), 0);
+}
+
+#[test]
+fn first_move_can_go_anywhere() {
+	let game = TicTacToe::new();
+
+	assert_eq!(game.get_cur_player(), Player::X);
+	assert_eq!(game.available_moves().len(), 81);
+	assert_eq!(game.get_result(), None);
+}
+
+#[test]
+fn move_sends_the_opponent_to_the_matching_board() {
+	let mut game = TicTacToe::new();
+
+	assert!(game.make_move_mut(&(4, 2)));
+	assert_eq!(game.get_cur_player(), Player::O);
+	assert_eq!(game.move_restriction, Some(2));
+	assert_eq!(game.available_moves(), (0..9).map(|cell| (2, cell)).collect::<Vec<_>>());
+
+	assert!(!game.make_move_mut(&(3, 0)), "moved outside the restriction");
+	assert!(game.make_move_mut(&(2, 4)));
+	assert!(!game.make_move_mut(&(4, 2)), "moved into a taken cell");
+	assert_eq!(game.available_moves().len(), 8);
+}
+
+#[test]
+fn completing_a_line_captures_the_board() {
+	let mut game = position(Rules::default(), ["XX.OO....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::X, Some(0));
+
+	assert_eq!(game.board_winner(0), None);
+	assert!(game.make_move_mut(&(0, 2)));
+	assert_eq!(game.board_winner(0), Some(Player::X));
+	assert!(!game.board_drawn(0));
+	assert_eq!(game.get_result(), None);
+}
+
+#[test]
+fn captured_board_can_not_be_played_in() {
+	let mut game = position(Rules::default(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, None);
+
+	assert!(!game.make_move_mut(&(0, 5)));
+	assert!(game.make_move_mut(&(1, 0)));
+
+	// being sent to the captured board is a free move that still can't go there
+	assert_eq!(game.move_restriction, None);
+	assert!(!game.make_move_mut(&(0, 5)));
+	assert!(game.available_moves().iter().all(|&(board, _)| board != 0));
+}
+
+#[test]
+fn restriction_is_reset_after_a_free_move() {
+	let mut game = position(Rules::default(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(1));
+
+	assert!(game.make_move_mut(&(1, 0)));
+	assert_eq!(game.move_restriction, None);
+
+	assert!(game.make_move_mut(&(7, 3)));
+	assert_eq!(game.move_restriction, Some(3));
+	assert!(game.available_moves().iter().all(|&(board, _)| board == 3));
+}
+
+#[test]
+fn line_of_boards_wins_the_game() {
+	let mut game = position(Rules::default(), [WON_O, WON_O, "OO.XX....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(2));
+
+	assert!(game.make_move_mut(&(2, 2)));
+	assert_eq!(game.get_result(), Some(GameResult::Win(Player::O)));
+	assert_eq!(game.get_winner(), Some(Player::O));
+	assert_eq!(game.get_cur_player(), Player::O);
+}
+
+#[test]
+fn moves_left_counts_every_open_board_even_when_sent_to_one() {
+	let game = position(Rules::default(), [WON_O, WON_O, "OO.XX....", DRAWN, DRAWN, DRAWN, DRAWN, EMPTY, DRAWN], Player::O, Some(2));
+
+	assert_eq!(game.available_moves().len(), 5);
+	assert_eq!(game.moves_left(), Some(14));
+	assert_eq!(TicTacToe::new().moves_left(), Some(81));
+}
+
+#[test]
+fn moves_left_with_any_empty_cell_counts_decided_boards() {
+	let game = position(any_empty_cell(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, None);
+
+	assert_eq!(game.moves_left(), Some(72 + 4));
+	assert_eq!(position(Rules::default(), [WON_X, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, None).moves_left(), Some(72));
+}
+
+#[test]
+fn finished_game_has_no_moves() {
+	let mut game = position(Rules::default(), [WON_O, WON_O, "OO.XX....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(2));
+	game.make_move_mut(&(2, 2));
+
+	assert!(game.available_moves().is_empty());
+	assert_eq!(game.moves().next(), None);
+	assert_eq!(game.random_move(&mut XorShiftRng::from_seed([1, 2, 3, 4])), None);
+	assert!(!game.make_move_mut(&(3, 0)));
+	assert!(game.make_move(&(3, 0)).is_none());
+}
+
+#[test]
+fn out_of_range_moves_are_rejected() {
+	let mut game = TicTacToe::new();
+
+	assert!(!game.make_move_mut(&(9, 0)));
+	assert!(!game.make_move_mut(&(0, 9)));
+	assert_eq!(game.available_moves().len(), 81);
+}
+
+/// Checks that the bitboard and reference implementations agree on a position.
+fn assert_same(game: &TicTacToe, reference: &Reference) {
+	assert_eq!(game.available_moves(), reference.available_moves());
+	assert_eq!(game.get_result(), reference.get_result());
+	assert_eq!(game.get_cur_player(), reference.get_cur_player());
+	assert_eq!(game.to_str(), reference.to_str());
+}
+
+/// Walks every line of play `depth` moves deep from both implementations, returning the number of
+/// positions reached.
+fn perft_same(game: &TicTacToe, reference: &Reference, depth: usize) -> u64 {
+	assert_same(game, reference);
+
+	if depth == 0 {
+		return 1;
+	}
+
+	game.available_moves().iter()
+		.map(|mv| perft_same(&game.make_move(mv).unwrap(), &reference.make_move(mv).unwrap(), depth - 1))
+		.sum()
+}
+
+#[test]
+fn bitboard_matches_reference_from_the_start() {
+	let leaves = perft_same(&TicTacToe::new(), &Reference::with_rules(Rules::default()), 4);
+	assert_eq!(leaves, perft(&TicTacToe::new(), 4));
+}
+
+#[test]
+fn bitboard_matches_reference_in_random_games() {
+	let variants = [
+		Rules::default(),
+		drawn_for_both(),
+		majority(),
+		any_empty_cell(),
+		Rules { drawn_boards_count_for_both: true, majority_tiebreak: true, free_move: FreeMove::AnyEmptyCell },
+	];
+	let mut rand = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+	for &rules in &variants {
+		for _ in 0..200 {
+			let mut game = TicTacToe::with_rules(rules);
+			let mut reference = Reference::with_rules(rules);
+
+			loop {
+				assert_same(&game, &reference);
+
+				// illegal moves must be rejected the same way
+				let guess = (rand.gen_range(0, 9), rand.gen_range(0, 9));
+				if game.clone().make_move_mut(&guess) != reference.clone().make_move_mut(&guess) {
+					panic!("implementations disagree on {:?} in\n{}", guess, game.to_str());
+				}
+
+				match game.random_move(&mut rand) {
+					Some(mv) => {
+						assert!(game.make_move_mut(&mv));
+						assert!(reference.make_move_mut(&mv));
+					},
+					None => break,
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn random_move_matches_choosing_from_available_moves() {
+	let mut game = TicTacToe::new();
+	let mut a = XorShiftRng::from_seed([5, 6, 7, 8]);
+	let mut b = a.clone();
+
+	while let Some(mv) = game.random_move(&mut a) {
+		assert_eq!(Some(&mv), b.choose(&game.available_moves()));
+		game.make_move_mut(&mv);
+	}
+
+	assert!(game.get_result().is_some());
+}
+
+#[test]
+fn start_position_notation() {
+	assert_eq!(TicTacToe::new().to_notation(), START_NOTATION);
+
+	let game = TicTacToe::from_notation(START_NOTATION, Rules::default()).unwrap();
+	assert_eq!(game.available_moves().len(), 81);
+	assert_eq!(game.get_cur_player(), Player::X);
+}
+
+#[test]
+fn notation_describes_cells_winners_restriction_and_player() {
+	let game = position(Rules::default(), [WON_X, EMPTY, "OO.XX....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, DRAWN], Player::O, Some(2));
+
+	assert_eq!(game.to_notation(), "XXXOO4/9/OO1XX4/9/9/9/9/9/XOXXOOOXX X8 2 O");
+}
+
+#[test]
+fn to_str_lays_the_boards_out_in_rows() {
+	// board 1 cell 2 is in the top row, board 0 cell 5 in the second and board 5 cell 6 in the
+	// bottom row of the middle band
+	let mut game = TicTacToe::new();
+	assert!(game.make_move_mut(&(1, 2)));
+	assert!(game.make_move_mut(&(2, 0)));
+	assert!(game.make_move_mut(&(0, 5)));
+	assert!(game.make_move_mut(&(5, 6)));
+
+	assert_eq!(game.to_str(), [
+		"       |     X | O     ",
+		"     X |       |       ",
+		"       |       |       ",
+		"-------+-------+-------",
+		"       |       |       ",
+		"       |       |       ",
+		"       |       | O     ",
+		"-------+-------+-------",
+		"       |       |       ",
+		"       |       |       ",
+		"       |       |       ",
+	].iter().map(|row| format!("{}\n", row)).collect::<String>());
+}
+
+#[test]
+fn notation_round_trips_through_random_games() {
+	let mut rand = XorShiftRng::from_seed([9, 10, 11, 12]);
+
+	for &rules in &[Rules::default(), any_empty_cell()] {
+		for _ in 0..50 {
+			let mut game = TicTacToe::with_rules(rules);
+
+			loop {
+				let copy = TicTacToe::from_notation(&game.to_notation(), rules).unwrap();
+				assert_eq!(copy.to_notation(), game.to_notation());
+				assert_eq!(copy.available_moves(), game.available_moves());
+				assert_eq!(copy.get_result(), game.get_result());
+				assert_eq!(copy.get_cur_player(), game.get_cur_player());
+				assert_eq!(copy.hash_key(), game.hash_key());
+
+				match game.random_move(&mut rand) {
+					Some(mv) => game.make_move_mut(&mv),
+					None => break,
+				};
+			}
+		}
+	}
+}
+
+#[test]
+fn transposed_move_orders_have_the_same_hash() {
+	let play = |moves: &[(usize, usize)]| {
+		let mut game = TicTacToe::new();
+		for mv in moves {
+			assert!(game.make_move_mut(mv));
+		}
+		game
+	};
+
+	let a = play(&[(0, 1), (1, 0), (0, 2), (2, 0)]);
+	let b = play(&[(0, 2), (2, 0), (0, 1), (1, 0)]);
+	assert_eq!(a.to_notation(), b.to_notation());
+	assert_eq!(a.hash_key(), b.hash_key());
+
+	let c = play(&[(0, 1), (1, 0), (0, 2), (2, 1)]);
+	assert_ne!(a.hash_key(), c.hash_key());
+}
+
+#[test]
+fn the_same_cells_with_different_board_winners_have_different_hashes() {
+	// X and O can both complete a line in board 0, and both cells send the next move to a closed
+	// board, so with free moves into won boards either can take board 0 first
+	let start = position(any_empty_cell(), ["XX.OO....", EMPTY, WON_X, EMPTY, EMPTY, DRAWN, EMPTY, EMPTY, EMPTY], Player::X, None);
+	let play = |moves: &[(usize, usize)]| {
+		let mut game = start.clone();
+		for mv in moves {
+			assert!(game.make_move_mut(mv));
+		}
+		game
+	};
+
+	let x_first = play(&[(0, 2), (0, 5), (3, 2), (4, 5)]);
+	let o_first = play(&[(3, 2), (0, 5), (0, 2), (4, 5)]);
+	assert_eq!(x_first.cells, o_first.cells);
+	assert_eq!((x_first.get_cur_player(), x_first.move_restriction), (o_first.get_cur_player(), o_first.move_restriction));
+	assert_eq!(x_first.board_winner(0), Some(Player::X));
+	assert_eq!(o_first.board_winner(0), Some(Player::O));
+
+	assert_ne!(x_first.hash_key(), o_first.hash_key());
+	assert_eq!(x_first.hash_key(), Some(x_first.zobrist()));
+	assert_eq!(o_first.hash_key(), Some(o_first.zobrist()));
+}
+
+#[test]
+fn hash_matches_working_it_out_again_in_random_games() {
+	let mut rand = XorShiftRng::from_seed([13, 14, 15, 16]);
+
+	for &rules in &[Rules::default(), drawn_for_both(), any_empty_cell()] {
+		for _ in 0..100 {
+			let mut game = TicTacToe::with_rules(rules);
+
+			while let Some(mv) = game.random_move(&mut rand) {
+				game.make_move_mut(&mv);
+				assert_eq!(game.hash_key(), Some(game.zobrist()));
+			}
+		}
+	}
+}
+
+#[test]
+fn bad_notation_is_rejected() {
+	let bad = [
+		"",
+		"9/9/9/9/9/9/9/9 9 - X",
+		"9/9/9/9/9/9/9/9/8 9 - X",
+		"9/9/9/9/9/9/9/9/91 9 - X",
+		"9/9/9/9/9/9/9/9/9 9 - Z",
+		"9/9/9/9/9/9/9/9/9 9 9 X",
+		"XXX6/9/9/9/9/9/9/9/9 9 - O",
+		"9/9/9/9/9/9/9/9/9 X8 - O",
+		"XXX6/9/9/9/9/9/9/9/9 X8 0 O",
+	];
+
+	for notation in &bad {
+		assert!(TicTacToe::from_notation(notation, Rules::default()).is_err(), "accepted {:?}", notation);
+	}
+}
+
+#[test]
+fn finished_game_notation_keeps_the_result() {
+	let mut game = position(Rules::default(), [WON_O, WON_O, "OO.XX....", EMPTY, EMPTY, EMPTY, EMPTY, EMPTY, EMPTY], Player::O, Some(2));
+	game.make_move_mut(&(2, 2));
+
+	let copy = TicTacToe::from_notation(&game.to_notation(), Rules::default()).unwrap();
+	assert_eq!(copy.get_result(), Some(GameResult::Win(Player::O)));
+	assert!(copy.available_moves().is_empty());
+}
+
+#[test]
+fn game_record_round_trips() {
+	let mut record = GameRecord::new(TicTacToe::with_rules(majority()));
+	for &mv in &[(4, 0), (0, 4), (4, 8)] {
+		assert!(record.push(mv));
+	}
+	assert!(!record.push((4, 0)));
+
+	let text = record.to_text();
+	assert_eq!(text, "position startpos\nrules majority-tiebreak\nmoves 40 04 48\n");
+
+	let copy = GameRecord::from_text(&text).unwrap();
+	assert_eq!(copy.moves(), record.moves());
+	assert_eq!(copy.start().rules(), majority());
+	assert_eq!(copy.position().to_notation(), record.position().to_notation());
+}
+
+#[test]
+fn game_record_from_a_position() {
+	let text = "# a comment\nposition 9/9/9/9/X8/9/9/9/9 9 0 O\n\nmoves 04 43\n";
+	let record = GameRecord::from_text(text).unwrap();
+
+	assert_eq!(record.start().to_notation(), "9/9/9/9/X8/9/9/9/9 9 0 O");
+	assert_eq!(record.position().to_notation(), "4O4/9/9/9/X2X5/9/9/9/9 9 3 O");
+	assert_eq!(GameRecord::from_text(&record.to_text()).unwrap().moves(), record.moves());
+}
+
+#[test]
+fn game_record_rejects_illegal_moves() {
+	assert!(GameRecord::from_text("position startpos\nmoves 40 40\n").is_err());
+	assert!(GameRecord::from_text("moves 4\n").is_err());
+	assert!(GameRecord::from_text("rules no-such-rule\n").is_err());
+	assert!(GameRecord::from_text("winner X\n").is_err());
+}
+
+#[test]
+fn saved_game_round_trips() {
+	let mut saved = SavedGame::new(TicTacToe::new(), vec![Player::O]);
+	assert!(saved.push((4, 0), None));
+	assert!(saved.push((0, 4), Some(MoveStats { total_sims: 1520, confidence: 0.512 })));
+	assert!(!saved.push((0, 4), None));
+
+	let text = saved.to_text();
+	assert_eq!(text, "position startpos\nmoves 40 04\nai O\nsims - 1520\nconfidence - 0.512\n");
+
+	let copy = SavedGame::from_text(&text).unwrap();
+	assert_eq!(copy.record.moves(), saved.record.moves());
+	assert_eq!(copy.ai_players, vec![Player::O]);
+	assert_eq!(copy.stats, saved.stats);
+	assert_eq!(copy.describe_move(1), "O played 04 (1520 sims, 51% confidence)");
+}
+
+#[test]
+fn saved_game_without_statistics_has_none_for_each_move() {
+	let saved = SavedGame::from_text("position startpos\nrules any-empty-cell\nmoves 40 04\nai X O\n").unwrap();
+
+	assert_eq!(saved.record.start().rules(), any_empty_cell());
+	assert_eq!(saved.ai_players, vec![Player::X, Player::O]);
+	assert_eq!(saved.stats, vec![None, None]);
+	assert_eq!(saved.describe_move(0), "X played 40");
+}
+
+#[test]
+fn saved_game_rejects_malformed_input() {
+	let moves = "position startpos\nmoves 40 04\n";
+	let read = |rest: &str| SavedGame::from_text(&format!("{}{}", moves, rest)).err();
+
+	assert_eq!(read("ai X Z\n"), Some("expected X or O, found Z".to_owned()));
+	assert_eq!(read("ai x\n"), Some("expected X or O, found x".to_owned()));
+	assert_eq!(read("sims 10 lots\nconfidence 0.5 0.5\n"), Some("lots is not a number of simulations".to_owned()));
+	assert_eq!(read("sims 10 20\nconfidence 0.5 high\n"), Some("high is not a confidence".to_owned()));
+	assert_eq!(read("sims 10\nconfidence 0.5\n"), Some("expected statistics for 2 moves".to_owned()));
+	assert_eq!(read("sims 10 20\n"), Some("expected statistics for 2 moves".to_owned()));
+	assert!(SavedGame::from_text("moves 40 40\nai X\n").is_err());
+}
@@ -0,0 +1,720 @@
+use std::rc::Rc;
+use std::cell::{ Cell, RefCell };
+use std::fs::File;
+use std::io::{ Read, Write };
+use std::time::{ Duration, Instant };
+
+use gtk;
+use gtk::prelude::*;
+use gtk::{ Window, Label, DrawingArea, EventBox, Paned, Button };
+use gtk::{ MenuBar, Menu, MenuItem, FileChooserDialog, Dialog, Grid, ComboBoxText, SpinButton };
+use gtk::{ WindowType, WindowPosition, Orientation, FileChooserAction, ResponseType };
+
+use tictactoe::game::{ MoveStats, Player, SavedGame, TicTacToe };
+use tictactoe::ai::{ Ai, Game, Limits, Proof, Request, Response };
+use config::Config;
+
+/// The strengths offered for an AI player, as a cap on the simulations it runs for each move.
+const STRENGTHS: [(&str, Option<u64>); 4] = [
+	("Easy", Some(500)),
+	("Medium", Some(5_000)),
+	("Hard", Some(50_000)),
+	("Full", None),
+];
+
+/// The sides offered in the New Game dialog, as the players played by a human.
+const SIDES: [(&str, &[Player]); 4] = [
+	("Human (X) vs AI (O)", &[Player::X]),
+	("AI (X) vs Human (O)", &[Player::O]),
+	("AI vs AI", &[]),
+	("Human vs Human", &[Player::X, Player::O]),
+];
+
+/// How the AI plays one side.
+#[derive(Clone, Copy)]
+struct AiSide {
+	turn_time: u64,
+	max_sims: Option<u64>,
+}
+
+/// Who plays each side of the current game.
+#[derive(Clone)]
+struct Players {
+	humans: Vec<Player>,
+	x: AiSide,
+	o: AiSide,
+}
+
+impl Players {
+	fn from_config(config: &Config) -> Players {
+		Players {
+			humans: config.humans.clone(),
+			x: AiSide { turn_time: config.x.turn_time, max_sims: None },
+			o: AiSide { turn_time: config.o.turn_time, max_sims: None },
+		}
+	}
+
+	fn is_human(&self, player: Player) -> bool {
+		self.humans.contains(&player)
+	}
+
+	fn ai_players(&self) -> Vec<Player> {
+		[Player::X, Player::O].iter().cloned().filter(|&p| !self.is_human(p)).collect()
+	}
+
+	fn side(&self, player: Player) -> AiSide {
+		match player {
+			Player::X => self.x,
+			Player::O => self.o,
+		}
+	}
+
+	/// The side whose AI's statistics are shown: the side to move if the AI plays it, otherwise
+	/// the other side, whose AI may be pondering.
+	fn shown(&self, to_move: Player) -> Player {
+		if self.is_human(to_move) { to_move.other() } else { to_move }
+	}
+
+	/// The search for the AI's move as `player`.
+	fn turn(&self, player: Player) -> Request<TicTacToe> {
+		let side = self.side(player);
+		Request::Go {
+			limits: Limits {
+				time: Some(Duration::from_secs(side.turn_time)),
+				sims: side.max_sims,
+				..Limits::default()
+			}
+		}
+	}
+}
+
+/// One AI for each side, so that the two sides can be set up differently. The AI for a side
+/// played by a human only follows the moves and is kept paused.
+struct Ais {
+	x: Ai<TicTacToe>,
+	o: Ai<TicTacToe>,
+}
+
+impl Ais {
+	fn new(start: &TicTacToe, config: &Config, players: &Players) -> Ais {
+		let ais = Ais {
+			x: Ai::with_settings(start.clone(), config.x.ai.clone()),
+			o: Ai::with_settings(start.clone(), config.o.ai.clone()),
+		};
+
+		if let Some(ponder) = config.ponder_against(&players.humans) {
+			for &(_, ai) in &ais.both() {
+				ai.send(Request::SetPonder(ponder.clone()));
+			}
+		}
+
+		ais
+	}
+
+	fn get(&self, player: Player) -> &Ai<TicTacToe> {
+		match player {
+			Player::X => &self.x,
+			Player::O => &self.o,
+		}
+	}
+
+	fn both(&self) -> [(Player, &Ai<TicTacToe>); 2] {
+		[(Player::X, &self.x), (Player::O, &self.o)]
+	}
+
+	/// Both AIs answer with `Response::Ok`.
+	fn make_move(&self, mv: (usize, usize)) {
+		self.x.make_move(mv);
+		self.o.make_move(mv);
+	}
+
+	/// Both AIs answer with `Response::Ok`.
+	fn undo(&self) {
+		self.x.send(Request::Undo);
+		self.o.send(Request::Undo);
+	}
+
+	fn pause(&self) {
+		self.x.send(Request::Pause);
+		self.o.send(Request::Pause);
+	}
+
+	/// Starts the turn in `game` once both AIs have caught up with it: the AI to move searches,
+	/// the other AI ponders if it plays a side, and AIs with nothing to do are paused.
+	fn start_turn(&self, game: &TicTacToe, players: &Players, replaying: bool) {
+		for &(player, ai) in &self.both() {
+			if game.get_result().is_some() || replaying || players.is_human(player) {
+				ai.send(Request::Pause);
+			} else {
+				// the game may have been paused before a move was taken back
+				ai.send(Request::Resume);
+				if game.get_cur_player() == player {
+					ai.send(players.turn(player));
+				}
+			}
+		}
+	}
+}
+
+/// Asks for the players of a new game, starting from `current`. Returns `None` if cancelled.
+fn new_game_dialog(parent: &Window, current: &Players) -> Option<Players> {
+	let ok: i32 = ResponseType::Ok.into();
+	let cancel: i32 = ResponseType::Cancel.into();
+	let dialog = Dialog::new_with_buttons(Some("New Game"), Some(parent), gtk::DIALOG_MODAL,
+		&[("Start", ok), ("Cancel", cancel)]);
+
+	let grid = Grid::new();
+	grid.set_border_width(8);
+	grid.set_row_spacing(8);
+	grid.set_column_spacing(8);
+
+	let sides = ComboBoxText::new();
+	for (i, &(name, humans)) in SIDES.iter().enumerate() {
+		sides.append_text(name);
+		if humans == current.humans.as_slice() {
+			sides.set_active(i as i32);
+		}
+	}
+	grid.attach(&Label::new("Players"), 0, 0, 1, 1);
+	grid.attach(&sides, 1, 0, 2, 1);
+
+	let mut ai_widgets = Vec::new();
+	for (row, &player) in [Player::X, Player::O].iter().enumerate() {
+		let side = current.side(player);
+		let row = row as i32 + 1;
+
+		let time = SpinButton::new_with_range(1.0, 600.0, 1.0);
+		time.set_value(side.turn_time as f64);
+
+		let strength = ComboBoxText::new();
+		for (i, &(name, max_sims)) in STRENGTHS.iter().enumerate() {
+			strength.append_text(name);
+			if max_sims == side.max_sims {
+				strength.set_active(i as i32);
+			}
+		}
+
+		grid.attach(&Label::new(format!("AI as {:?}: seconds per move", player).as_str()), 0, row, 1, 1);
+		grid.attach(&time, 1, row, 1, 1);
+		grid.attach(&strength, 2, row, 1, 1);
+		ai_widgets.push((time, strength));
+	}
+
+	dialog.get_content_area().pack_start(&grid, true, true, 0);
+	dialog.show_all();
+
+	let players = if dialog.run() == ok {
+		let side = |i: usize| {
+			let (ref time, ref strength) = ai_widgets[i];
+			AiSide {
+				turn_time: time.get_value_as_int().max(1) as u64,
+				max_sims: STRENGTHS.get(strength.get_active() as usize).and_then(|&(_, max_sims)| max_sims),
+			}
+		};
+
+		Some(Players {
+			humans: SIDES.get(sides.get_active() as usize).map(|&(_, humans)| humans.to_vec()).unwrap_or_default(),
+			x: side(0),
+			o: side(1),
+		})
+	} else {
+		None
+	};
+
+	dialog.destroy();
+	players
+}
+
+/// Shows the position after the first `index` moves of a loaded game.
+fn show_replay(saved: &SavedGame, index: usize, game: &RefCell<TicTacToe>, label: &Label) {
+	*game.borrow_mut() = saved.record.position_after(index);
+
+	let last_move = if index > 0 { saved.describe_move(index - 1) } else { "Start".to_owned() };
+	label.set_markup(&format!("<tt>Replay: move {} of {}\n{}</tt>", index, saved.record.moves().len(), last_move));
+}
+
+pub fn run(config: &Config) {
+	let config = config.clone();
+	let players = Rc::new(RefCell::new(Players::from_config(&config)));
+	let game = Rc::new(RefCell::new(TicTacToe::new()));
+	let ais = Rc::new(RefCell::new(Ais::new(&game.borrow(), &config, &players.borrow())));
+
+	// the game so far, with the AI's statistics from the last `Info` before each move
+	let saved = Rc::new(RefCell::new(SavedGame::new(game.borrow().clone(), players.borrow().ai_players())));
+	let last_stats: Rc<Cell<Option<MoveStats>>> = Rc::new(Cell::new(None));
+
+	// how many moves of a loaded game are shown, or `None` while playing
+	let replay: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+
+	// moves that were taken back, most recent last
+	let redo: Rc<RefCell<Vec<((usize, usize), Option<MoveStats>)>>> = Rc::new(RefCell::new(Vec::new()));
+	// replies the AIs owe for changes of position, one from each AI per change; a search that ends
+	// before they have all arrived was for an old position
+	let pending: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+	// when the current turn started, for the AI's time left
+	let last_move = Rc::new(Cell::new(Instant::now()));
+
+	if gtk::init().is_err() {
+		println!("Failed to initialize GTK.");
+		return;
+	}
+
+	
+
+	let draw_area = DrawingArea::new();
+	{
+		let g = game.clone();
+		draw_area.connect_draw(move |this, ctx| {
+			let w = this.get_allocated_width() as f64;
+			let h = this.get_allocated_height() as f64;
+
+			ctx.set_source_rgb(1.0, 1.0, 1.0);
+			ctx.paint();
+
+			g.borrow().draw(ctx, w, h);
+
+			Inhibit(false)
+		});
+	}
+
+	let event_box = EventBox::new();
+	event_box.add(&draw_area);
+	{
+		let g = game.clone();
+		let ais = ais.clone();
+		let da = draw_area.clone();
+		let saved = saved.clone();
+		let last_stats = last_stats.clone();
+		let replay = replay.clone();
+		let redo = redo.clone();
+		let pending = pending.clone();
+		let players = players.clone();
+
+		event_box.connect_button_press_event(move |this, button| {
+
+			if replay.get().is_none() && players.borrow().is_human(g.borrow().get_cur_player()) {
+				let w = this.get_allocated_width() as f64;
+				let h = this.get_allocated_height() as f64;
+				let (x, y) = button.get_position();
+
+				if let Some(mv) = g.borrow_mut().click(w, h, x, y) {
+					saved.borrow_mut().push(mv, last_stats.get());
+					redo.borrow_mut().clear();
+					ais.borrow().make_move(mv);
+					pending.set(pending.get() + 2);
+				}
+
+				da.queue_draw();
+			}
+
+			Inhibit(false)
+		});
+	}
+
+	let player_label = Label::new("<tt>Player: <span foreground=\"#000000\">X</span></tt>");
+	let best_move_label = Label::new("<tt>Best Move: None</tt>");
+	let confidence_label = Label::new("<tt>Confidence: <span foreground=\"#ffff00\">0%</span></tt>");
+	let num_sims_label = Label::new("<tt>Simulations: 0</tt>");
+	let time_label = Label::new("<tt>Elapsed Time: 0 seconds</tt>");
+	let rate_label = Label::new("<tt>0 sims/second</tt>");
+	let reuse_label = Label::new("<tt>Tree Reuse: None</tt>");
+	let tree_label = Label::new("<tt>Tree: 0 nodes</tt>");
+	let ai_time_left_label = Label::new("");
+	let replay_label = Label::new("");
+	let status_label = Label::new("");
+	player_label.set_xalign(0.0);
+	best_move_label.set_xalign(0.0);
+	confidence_label.set_xalign(0.0);
+	num_sims_label.set_xalign(0.0);
+	time_label.set_xalign(0.0);
+	rate_label.set_xalign(0.0);
+	reuse_label.set_xalign(0.0);
+	tree_label.set_xalign(0.0);
+	ai_time_left_label.set_xalign(0.0);
+	replay_label.set_xalign(0.0);
+	status_label.set_xalign(0.0);
+
+	let back_button = Button::new_with_label("< Back");
+	let forward_button = Button::new_with_label("Forward >");
+	back_button.set_sensitive(false);
+	forward_button.set_sensitive(false);
+
+	let undo_button = Button::new_with_label("Undo");
+	let redo_button = Button::new_with_label("Redo");
+
+	let history_buttons = gtk::Box::new(Orientation::Horizontal, 8);
+	history_buttons.pack_start(&undo_button, false, false, 0);
+	history_buttons.pack_start(&redo_button, false, false, 0);
+
+	{
+		let g = game.clone();
+		let ais = ais.clone();
+		let saved = saved.clone();
+		let replay = replay.clone();
+		let redo = redo.clone();
+		let pending = pending.clone();
+		let da = draw_area.clone();
+		let players = players.clone();
+
+		// takes back moves until it is a human's turn again
+		undo_button.connect_clicked(move |_| {
+			if replay.get().is_some() {
+				return;
+			}
+
+			let players = players.borrow();
+			let mut saved = saved.borrow_mut();
+			while let Some(entry) = saved.pop() {
+				redo.borrow_mut().push(entry);
+				ais.borrow().undo();
+				pending.set(pending.get() + 2);
+
+				if players.humans.is_empty() || players.is_human(saved.record.position().get_cur_player()) {
+					break;
+				}
+			}
+
+			*g.borrow_mut() = saved.record.position();
+			da.queue_draw();
+		});
+	}
+	{
+		let g = game.clone();
+		let ais = ais.clone();
+		let saved = saved.clone();
+		let replay = replay.clone();
+		let redo = redo.clone();
+		let pending = pending.clone();
+		let da = draw_area.clone();
+		let players = players.clone();
+
+		// plays the moves that were taken back until it is a human's turn again
+		redo_button.connect_clicked(move |_| {
+			if replay.get().is_some() {
+				return;
+			}
+
+			let players = players.borrow();
+			let mut saved = saved.borrow_mut();
+			while let Some((mv, stats)) = redo.borrow_mut().pop() {
+				saved.push(mv, stats);
+				ais.borrow().make_move(mv);
+				pending.set(pending.get() + 2);
+
+				let position = saved.record.position();
+				if players.humans.is_empty() || players.is_human(position.get_cur_player()) || position.get_result().is_some() {
+					break;
+				}
+			}
+
+			*g.borrow_mut() = saved.record.position();
+			da.queue_draw();
+		});
+	}
+
+	let replay_buttons = gtk::Box::new(Orientation::Horizontal, 8);
+	replay_buttons.pack_start(&back_button, false, false, 0);
+	replay_buttons.pack_start(&forward_button, false, false, 0);
+
+	for &(button, step) in &[(&back_button, -1), (&forward_button, 1)] {
+		let g = game.clone();
+		let saved = saved.clone();
+		let replay = replay.clone();
+		let label = replay_label.clone();
+		let da = draw_area.clone();
+
+		button.connect_clicked(move |_| {
+			if let Some(index) = replay.get() {
+				let index = index as isize + step;
+				if index >= 0 && index as usize <= saved.borrow().record.moves().len() {
+					replay.set(Some(index as usize));
+					show_replay(&saved.borrow(), index as usize, &g, &label);
+					da.queue_draw();
+				}
+			}
+		});
+	}
+
+	let right_container = gtk::Box::new(Orientation::Vertical, 8);
+	right_container.set_border_width(8);
+	right_container.pack_start(&player_label, false, false, 0);
+	right_container.pack_start(&best_move_label, false, false, 0);
+	right_container.pack_start(&confidence_label, false, false, 0);
+	right_container.pack_start(&num_sims_label, false, false, 0);
+	right_container.pack_start(&time_label, false, false, 0);
+	right_container.pack_start(&rate_label, false, false, 0);
+	right_container.pack_start(&reuse_label, false, false, 0);
+	right_container.pack_start(&tree_label, false, false, 0);
+	right_container.pack_start(&ai_time_left_label, false, false, 0);
+	right_container.pack_start(&history_buttons, false, false, 0);
+	right_container.pack_start(&replay_label, false, false, 0);
+	right_container.pack_start(&replay_buttons, false, false, 0);
+	right_container.pack_start(&status_label, false, false, 0);
+
+	let container = Paned::new(Orientation::Horizontal);
+	container.set_position(900);
+	container.pack1(&event_box, true, true);
+	container.pack2(&right_container, false, true);
+
+
+	let window = Window::new(WindowType::Toplevel);
+	window.set_title("Ultimate Tic Tac Toe AI");
+	window.set_position(WindowPosition::Center);
+	window.set_default_size(1200, 720);
+	{
+		let ais = ais.clone();
+		window.connect_delete_event(move |_, _| {
+			ais.borrow().x.send(Request::Quit);
+			ais.borrow().o.send(Request::Quit);
+			gtk::main_quit();
+			Inhibit(false)
+		});
+	}
+
+	let new_item = MenuItem::new_with_label("New Game...");
+	let open_item = MenuItem::new_with_label("Open...");
+	let save_item = MenuItem::new_with_label("Save...");
+	let file_menu = Menu::new();
+	file_menu.append(&new_item);
+	file_menu.append(&open_item);
+	file_menu.append(&save_item);
+
+	let file_item = MenuItem::new_with_label("File");
+	file_item.set_submenu(Some(&file_menu));
+	let menu_bar = MenuBar::new();
+	menu_bar.append(&file_item);
+
+	let ok: i32 = ResponseType::Ok.into();
+	let cancel: i32 = ResponseType::Cancel.into();
+	{
+		let g = game.clone();
+		let ais = ais.clone();
+		let players = players.clone();
+		let saved = saved.clone();
+		let last_stats = last_stats.clone();
+		let replay = replay.clone();
+		let redo = redo.clone();
+		let pending = pending.clone();
+		let last_move = last_move.clone();
+		let parent = window.clone();
+		let status = status_label.clone();
+		let label = replay_label.clone();
+		let back = back_button.clone();
+		let forward = forward_button.clone();
+		let undo_button = undo_button.clone();
+		let redo_button = redo_button.clone();
+		let da = draw_area.clone();
+
+		// replaces the AIs with fresh ones and starts again from the empty board
+		new_item.connect_activate(move |_| {
+			let new_players = match new_game_dialog(&parent, &players.borrow()) {
+				Some(new_players) => new_players,
+				None => return,
+			};
+
+			let start = TicTacToe::new();
+			*ais.borrow_mut() = Ais::new(&start, &config, &new_players);
+			let ais = ais.borrow();
+
+			*g.borrow_mut() = start.clone();
+			*saved.borrow_mut() = SavedGame::new(start.clone(), new_players.ai_players());
+			last_stats.set(None);
+			replay.set(None);
+			redo.borrow_mut().clear();
+			pending.set(0);
+			last_move.set(Instant::now());
+
+			for &(_, ai) in &ais.both() {
+				ai.send(Request::Info);
+			}
+			ais.start_turn(&start, &new_players, false);
+			*players.borrow_mut() = new_players;
+
+			back.set_sensitive(false);
+			forward.set_sensitive(false);
+			undo_button.set_sensitive(true);
+			redo_button.set_sensitive(true);
+			label.set_text("");
+			status.set_text("New game");
+			da.queue_draw();
+		});
+	}
+	{
+		let saved = saved.clone();
+		let parent = window.clone();
+		let status = status_label.clone();
+
+		save_item.connect_activate(move |_| {
+			let dialog = FileChooserDialog::new(Some("Save Game"), Some(&parent), FileChooserAction::Save);
+			dialog.add_buttons(&[("Save", ok), ("Cancel", cancel)]);
+			dialog.set_do_overwrite_confirmation(true);
+			dialog.set_current_name("game.uttt");
+
+			if dialog.run() == ok {
+				if let Some(path) = dialog.get_filename() {
+					let text = saved.borrow().to_text();
+					match File::create(&path).and_then(|mut file| file.write_all(text.as_bytes())) {
+						Ok(()) => status.set_text(&format!("Saved {}", path.display())),
+						Err(e) => status.set_text(&format!("Couldn't save {}: {}", path.display(), e)),
+					}
+				}
+			}
+
+			dialog.destroy();
+		});
+	}
+	{
+		let g = game.clone();
+		let ais = ais.clone();
+		let saved = saved.clone();
+		let replay = replay.clone();
+		let parent = window.clone();
+		let status = status_label.clone();
+		let label = replay_label.clone();
+		let back = back_button.clone();
+		let forward = forward_button.clone();
+		let undo_button = undo_button.clone();
+		let redo_button = redo_button.clone();
+		let da = draw_area.clone();
+
+		open_item.connect_activate(move |_| {
+			let dialog = FileChooserDialog::new(Some("Open Game"), Some(&parent), FileChooserAction::Open);
+			dialog.add_buttons(&[("Open", ok), ("Cancel", cancel)]);
+
+			if dialog.run() == ok {
+				if let Some(path) = dialog.get_filename() {
+					let mut text = String::new();
+					let loaded = File::open(&path)
+						.and_then(|mut file| file.read_to_string(&mut text))
+						.map_err(|e| e.to_string())
+						.and_then(|_| SavedGame::from_text(&text));
+
+					match loaded {
+						Ok(loaded) => {
+							// the AI has nothing to think about while replaying
+							ais.borrow().pause();
+
+							let index = loaded.record.moves().len();
+							show_replay(&loaded, index, &g, &label);
+							*saved.borrow_mut() = loaded;
+							replay.set(Some(index));
+
+							back.set_sensitive(true);
+							forward.set_sensitive(true);
+							undo_button.set_sensitive(false);
+							redo_button.set_sensitive(false);
+							status.set_text(&format!("Opened {}", path.display()));
+							da.queue_draw();
+						},
+						Err(msg) => status.set_text(&format!("Couldn't open {}: {}", path.display(), msg)),
+					}
+				}
+			}
+
+			dialog.destroy();
+		});
+	}
+
+	let main_container = gtk::Box::new(Orientation::Vertical, 0);
+	main_container.pack_start(&menu_bar, false, false, 0);
+	main_container.pack_start(&container, true, true, 0);
+
+	window.add(&main_container);
+	window.show_all();
+
+	{
+		for &(_, ai) in &ais.borrow().both() {
+			ai.send(Request::Info);
+		}
+		ais.borrow().start_turn(&game.borrow(), &players.borrow(), false);
+
+		let da = draw_area.clone();
+
+		gtk::idle_add(move || {
+
+			let ais = ais.borrow();
+			for &(from, ai) in &ais.both() {
+				while let Some(res) = ai.recv() {
+					match res {
+						Response::Info { best_move, confidence, proof, total_sims, time_elapsed, tree_reuse, nodes, memory } => {
+							// each AI keeps answering, but only one of them is shown at a time
+							ai.send(Request::Info);
+
+							let player = game.borrow().get_cur_player();
+							let players = players.borrow();
+							if from != players.shown(player) {
+								continue;
+							}
+							last_stats.set(Some(MoveStats { total_sims, confidence }));
+
+							let move_str = if !players.is_human(player) {
+								best_move.map(|i| format!("{:?}", i)).unwrap_or(String::from("None"))
+							} else {
+								"Hidden".to_owned()
+							};
+							let time = time_elapsed.as_secs();
+							let subsec_time = time as f64 + (time_elapsed.subsec_nanos() as f64 / 1_000_000_000.0);
+							let rate = (total_sims as f64 / subsec_time).floor();
+							let confidence_pct = (confidence*100.0).floor();
+							let confidence_col = format!("#{:02x}{:02x}00", (255.0*(1.0-confidence)) as u8, (255.0*confidence) as u8);
+							let proof_str = match proof {
+								Some(Proof::Win) => " (proven win)",
+								Some(Proof::Draw) => " (proven draw)",
+								Some(Proof::Loss) => " (proven loss)",
+								None => "",
+							};
+
+							best_move_label.set_markup(&format!("<tt>Best Move: {}</tt>", move_str));
+							confidence_label.set_markup(&format!("<tt>Confidence: <span foreground=\"{}\">{}%</span>{}</tt>", confidence_col, confidence_pct, proof_str));
+							num_sims_label.set_markup(&format!("<tt>Simulations: {}</tt>", total_sims));
+							time_label.set_markup(&format!("<tt>Elapsed Time: {} seconds</tt>", time));
+							rate_label.set_markup(&format!("<tt>{} sims/second</tt>", rate));
+							if let Some(reuse) = tree_reuse {
+								reuse_label.set_markup(&format!("<tt>Tree Reuse: {}% ({} sims)</tt>", (reuse.fraction()*100.0).floor(), reuse.sims_kept));
+							}
+							tree_label.set_markup(&format!("<tt>Tree: {} nodes ({:.1} MB)</tt>", nodes, memory as f64 / (1024.0 * 1024.0)));
+							if !players.is_human(player) {
+								let turn_time = players.side(player).turn_time;
+								let ai_time = Instant::now().duration_since(last_move.get()).as_secs();
+								if ai_time <= turn_time {
+									ai_time_left_label.set_markup(&format!("<tt>{} seconds left</tt>", turn_time - ai_time));
+								}
+							} else {
+								ai_time_left_label.set_text("");
+							}
+
+							let player_str = if player == Player::X {"X"} else {"O"};
+							let player_col = if player == Player::X {"#ff0000"} else {"#0000ff"};
+							player_label.set_markup(&format!("<tt>Player: <span foreground=\"{}\">{}</span></tt>", player_col, player_str));
+						},
+						Response::Ok => {
+							pending.set(pending.get().saturating_sub(1));
+							if pending.get() > 0 {
+								// wait until both AIs have caught up with the position on the board
+								continue;
+							}
+							last_move.set(Instant::now());
+
+							ais.start_turn(&game.borrow(), &players.borrow(), replay.get().is_some());
+						},
+						Response::Done { best_move: Some(mv), .. } if replay.get().is_none() && pending.get() == 0
+							&& from == game.borrow().get_cur_player() && !players.borrow().is_human(from) => {
+							game.borrow_mut().make_move_mut(&mv);
+							saved.borrow_mut().push(mv, last_stats.get());
+							redo.borrow_mut().clear();
+							da.queue_draw();
+
+							ais.make_move(mv);
+							pending.set(pending.get() + 2);
+						},
+						Response::Done { .. } => { /* no moves left, a search for an old position, or replaying a loaded game */ },
+					};
+				}
+			}
+
+			Continue(true)
+		});
+	}
+
+	gtk::main();
+}
@@ -0,0 +1,14 @@
+//! Monte Carlo tree search based AI for Ultimate TicTacToe.
+//!
+//! The `ai` module contains the game-agnostic search engine, which runs on a background thread
+//! and is driven through `Request`s and `Response`s. The `game` module contains the Ultimate
+//! TicTacToe rules the engine is normally used with.
+
+extern crate rand;
+extern crate num_cpus;
+
+#[cfg(feature = "gui")]
+extern crate cairo;
+
+pub mod ai;
+pub mod game;
@@ -0,0 +1,451 @@
+use std::collections::VecDeque;
+use std::io::{ self, BufRead };
+use std::thread;
+use std::sync::mpsc::{ channel, Receiver, TryRecvError };
+use std::time::{ Duration, Instant };
+
+use tictactoe::game::{ self, Player, Rules, TicTacToe };
+use tictactoe::ai::{ Ai, Game, Limits, Ponder, Proof, Request, Response, Settings };
+use config::{ Config, Side };
+
+const POLL_INTERVAL: u64 = 20; // milliseconds
+const INFO_INTERVAL: u64 = 500; // milliseconds
+
+struct Search {
+	start: Instant,
+	start_sims: u64,
+	last_info: Instant,
+	infinite: bool,
+	stopped: bool,
+	awaiting_info: bool,
+}
+
+struct Engine {
+	ai: Ai<TicTacToe>,
+	start: TicTacToe,
+	moves: Vec<(usize, usize)>,
+	ponder: Option<Ponder>,
+}
+
+impl Engine {
+	fn new(settings: Settings, ponder: Option<Ponder>) -> Self {
+		let ai = Ai::with_settings(TicTacToe::new(), settings);
+		if let Some(ref p) = ponder {
+			ai.send(Request::SetPonder(p.clone()));
+		}
+
+		Engine {
+			ai,
+			start: TicTacToe::new(),
+			moves: Vec::new(),
+			ponder,
+		}
+	}
+
+	fn set_ponder(&mut self, ponder: Ponder) {
+		self.ai.send(Request::SetPonder(ponder.clone()));
+		self.ponder = Some(ponder);
+	}
+
+	/// Moves the AI to the position reached by playing `moves` from `start`, taking back moves
+	/// rather than starting again where the two positions share their first moves, so that as
+	/// much of the search tree as possible is kept.
+	fn set_position(&mut self, start: TicTacToe, moves: Vec<(usize, usize)>) {
+		if start.to_notation() != self.start.to_notation() || start.rules() != self.start.rules() {
+			self.ai.send(Request::SetPosition(start.clone()));
+			self.wait_ok();
+
+			self.start = start;
+			self.moves.clear();
+		}
+
+		let common = self.moves.iter().zip(&moves).take_while(|&(a, b)| a == b).count();
+		for _ in common..self.moves.len() {
+			self.ai.send(Request::Undo);
+			self.wait_ok();
+		}
+
+		for mv in &moves[common..] {
+			self.ai.make_move(*mv);
+			self.wait_ok();
+		}
+
+		self.moves = moves;
+	}
+
+	/// Waits for the AI to acknowledge a change of position.
+	fn wait_ok(&self) {
+		while let Response::Info { .. } = self.ai.recv_blocking() {}
+	}
+
+	fn total_sims(&self) -> u64 {
+		self.ai.send(Request::Info);
+		loop {
+			if let Response::Info { total_sims, .. } = self.ai.recv_blocking() {
+				return total_sims;
+			}
+		}
+	}
+}
+
+/// Parses `startpos` or `notation CELLS WINNERS RESTRICTION PLAYER`, optionally followed by
+/// `moves ...`, for a game played with `rules`.
+fn parse_position(args: &[&str], rules: Rules) -> Result<(TicTacToe, Vec<(usize, usize)>), String> {
+	let (start, rest) = match args.split_first() {
+		Some((&"startpos", rest)) => (TicTacToe::with_rules(rules), rest),
+		Some((&"notation", rest)) if rest.len() >= 4 => (TicTacToe::from_notation(&rest[..4].join(" "), rules)?, &rest[4..]),
+		_ => return Err("position must start with `startpos` or `notation`".to_owned()),
+	};
+
+	let mut game = start.clone();
+	let mut moves = Vec::new();
+	match rest.first() {
+		None => return Ok((start, moves)),
+		Some(&"moves") => {},
+		Some(other) => return Err(format!("expected `moves`, found `{}`", other)),
+	}
+
+	for arg in &rest[1..] {
+		match game::parse_move(arg) {
+			Some(mv) if game.make_move_mut(&mv) => moves.push(mv),
+			_ => return Err(format!("illegal move {}", arg)),
+		}
+	}
+
+	Ok((start, moves))
+}
+
+/// An option set with `setoption`.
+#[derive(Debug, PartialEq)]
+enum EngineOption {
+	Ponder(Ponder),
+	Rules(Rules),
+}
+
+/// Parses `setoption name NAME value VALUE`. The options are `ponder`, which takes `on`, `off` or a
+/// number of simulations to ponder for after each move, and `rules`, which takes `standard` or
+/// the rule variants to play with (see `Rules::variants`), separated by spaces, for the positions
+/// set after it.
+fn parse_option(args: &[&str]) -> Result<EngineOption, String> {
+	match args {
+		[name_kw, name, value_kw, value @ ..] if *name_kw == "name" && *value_kw == "value" && !value.is_empty() => {
+			if name.eq_ignore_ascii_case("ponder") {
+				let ponder = match *value {
+					["on"] => Some(Ponder::On),
+					["off"] => Some(Ponder::Off),
+					[sims] => sims.parse().ok().map(|n| Ponder::Limited(Limits { sims: Some(n), ..Limits::default() })),
+					_ => None,
+				};
+				ponder.map(EngineOption::Ponder).ok_or_else(|| "ponder expects on, off or a number of simulations".to_owned())
+			} else if name.eq_ignore_ascii_case("rules") {
+				match *value {
+					["standard"] => Ok(EngineOption::Rules(Rules::default())),
+					_ => Rules::from_variants(value.iter().cloned()).map(EngineOption::Rules),
+				}
+			} else {
+				Err(format!("unknown option {}", name))
+			}
+		},
+		_ => Err("expected `setoption name NAME value VALUE`".to_owned()),
+	}
+}
+
+/// Parses the parameters of `go`, searching for the configured turn time if there are none.
+fn parse_limits(args: &[&str], side: &Side) -> Result<Limits, String> {
+	let mut limits = Limits::default();
+
+	if args.is_empty() {
+		return Ok(side.turn_limits());
+	}
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let mut value = || args.next()
+			.and_then(|v| v.parse::<u64>().ok())
+			.ok_or_else(|| format!("{} expects a number", arg));
+
+		match *arg {
+			"movetime" => limits.time = Some(Duration::from_millis(value()?)),
+			"sims" => limits.sims = Some(value()?),
+			"nodes" => limits.nodes = Some(value()? as usize),
+			"depth" => limits.depth = Some(value()? as usize),
+			"infinite" => {},
+			other => return Err(format!("unknown go parameter {}", other)),
+		}
+	}
+
+	Ok(limits)
+}
+
+fn format_best_move(best_move: Option<(usize, usize)>) -> String {
+	best_move.map(|mv| game::format_move(&mv)).unwrap_or_else(|| "none".to_owned())
+}
+
+/// Prints an `info` line, which also gives the size of the tree, in nodes and kilobytes, when it
+/// is known.
+fn print_info(elapsed: Duration, best_move: Option<(usize, usize)>, confidence: f64, sims: u64, tree: Option<(usize, usize)>, proof: Option<Proof>) {
+	let ms = elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000;
+	let tree = tree.map(|(nodes, memory)| format!(" tree_nodes {} memory_kb {}", nodes, memory / 1024)).unwrap_or_default();
+	let proof = match proof {
+		Some(Proof::Win) => " proven win",
+		Some(Proof::Draw) => " proven draw",
+		Some(Proof::Loss) => " proven loss",
+		None => "",
+	};
+
+	println!("info time {} total_sims {}{} confidence {:.3}{} bestmove {}",
+		ms, sims, tree, confidence, proof, format_best_move(best_move));
+}
+
+/// Reads lines from stdin on a separate thread so that `stop` can arrive while searching.
+fn spawn_reader() -> Receiver<String> {
+	let (to_engine, from_stdin) = channel();
+
+	thread::spawn(move || {
+		let stdin = io::stdin();
+		for line in stdin.lock().lines() {
+			match line {
+				Ok(line) => if to_engine.send(line).is_err() { break },
+				Err(_) => break,
+			}
+		}
+	});
+
+	from_stdin
+}
+
+/// Speaks the Universal Tic-Tac-Toe Protocol over stdin and stdout.
+///
+/// Supported commands are `utttp`, `isready`, `newgame`, `setoption name ponder|rules value ...`,
+/// `position startpos|notation ... [moves ...]`, `go [movetime MS] [sims N] [nodes N] [depth N] [infinite]`,
+/// `stop` and `quit`. Moves are written as two digits, the board followed by the cell. Commands
+/// are handled in order; while searching, any command other than `stop`, `isready` and `quit`
+/// waits until the search finishes, so a whole session can be piped in at once.
+pub fn run(config: &Config) {
+	let lines = spawn_reader();
+	let mut queued = VecDeque::new();
+	let mut stdin_closed = false;
+
+	// the engine plays whichever side it is asked to, so it uses the settings given for X
+	let side = config.side(Player::X);
+	let mut engine = Engine::new(side.ai.clone(), config.ponder.clone());
+	let mut search: Option<Search> = None;
+	// the rules of the positions set from now on
+	let mut rules = Rules::default();
+
+	loop {
+		if let Some(mut s) = search.take() {
+			loop {
+				match lines.try_recv() {
+					Ok(line) => queued.push_back(line),
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => {
+						stdin_closed = true;
+						break;
+					},
+				}
+			}
+
+			if stdin_closed && s.infinite && !s.stopped {
+				engine.ai.send(Request::Stop);
+				s.stopped = true;
+			}
+
+			// commands are handled in the order they arrived, so only those directly following
+			// the `go` apply to this search
+			while let (false, Some(cmd)) = (s.stopped, queued.front().map(|line| line.trim().to_owned())) {
+				match cmd.as_str() {
+					"stop" => {
+						engine.ai.send(Request::Stop);
+						s.stopped = true;
+					},
+					"isready" => println!("readyok"),
+					"quit" => return,
+					_ => break,
+				}
+				queued.pop_front();
+			}
+
+			if !s.awaiting_info && s.last_info.elapsed() >= Duration::from_millis(INFO_INTERVAL) {
+				engine.ai.send(Request::Info);
+				s.awaiting_info = true;
+			}
+
+			// nothing after `Done` belongs to this search, so an `Info` queued behind it is left for
+			// the loop below to discard rather than printed after `bestmove`
+			let mut done = false;
+			while let Some(res) = engine.ai.recv() {
+				match res {
+					Response::Info { best_move, confidence, proof, total_sims, nodes, memory, .. } => {
+						print_info(s.start.elapsed(), best_move, confidence, total_sims - s.start_sims, Some((nodes, memory)), proof);
+						s.last_info = Instant::now();
+						s.awaiting_info = false;
+					},
+					Response::Done { best_move, confidence, sims, time_elapsed, .. } => {
+						print_info(time_elapsed, best_move, confidence, sims, None, None);
+						println!("bestmove {}", format_best_move(best_move));
+						done = true;
+						break;
+					},
+					Response::Ok => {},
+				}
+			}
+
+			if done {
+				// make sure an Info reply doesn't arrive in the middle of the next command
+				while s.awaiting_info {
+					if let Response::Info { .. } = engine.ai.recv_blocking() {
+						s.awaiting_info = false;
+					}
+				}
+			} else {
+				search = Some(s);
+				thread::sleep(Duration::from_millis(POLL_INTERVAL));
+			}
+
+			continue;
+		}
+
+		let line = match queued.pop_front() {
+			Some(line) => line,
+			None => match lines.recv() {
+				Ok(line) => line,
+				Err(_) => return,
+			},
+		};
+
+		let words: Vec<&str> = line.split_whitespace().collect();
+		match words.split_first() {
+			Some((&"utttp", _)) => {
+				println!("id name tictactoe {}", env!("CARGO_PKG_VERSION"));
+				println!("id author {}", env!("CARGO_PKG_AUTHORS"));
+				println!("utttpok");
+			},
+			Some((&"isready", _)) => println!("readyok"),
+			Some((&"newgame", _)) => {
+				let ponder = engine.ponder.take();
+				engine = Engine::new(side.ai.clone(), ponder);
+			},
+			Some((&"setoption", args)) => match parse_option(args) {
+				Ok(EngineOption::Ponder(ponder)) => engine.set_ponder(ponder),
+				Ok(EngineOption::Rules(r)) => rules = r,
+				Err(msg) => println!("info string {}", msg),
+			},
+			Some((&"position", args)) => match parse_position(args, rules) {
+				Ok((start, moves)) => engine.set_position(start, moves),
+				Err(msg) => println!("info string {}", msg),
+			},
+			Some((&"go", args)) => match parse_limits(args, side) {
+				Ok(limits) => {
+					let start_sims = engine.total_sims();
+					let infinite = limits == Limits::default();
+					engine.ai.send(Request::Go { limits });
+
+					search = Some(Search {
+						start: Instant::now(),
+						start_sims,
+						last_info: Instant::now(),
+						infinite,
+						stopped: false,
+						awaiting_info: false,
+					});
+				},
+				Err(msg) => println!("info string {}", msg),
+			},
+			Some((&"stop", _)) => { /* not searching */ },
+			Some((&"quit", _)) => return,
+			Some((other, _)) => println!("info string unknown command {}", other),
+			None => {},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tictactoe::game::FreeMove;
+
+	#[test]
+	fn positions_start_from_startpos_or_notation() {
+		let (start, moves) = parse_position(&["startpos", "moves", "40", "04"], Rules::default()).unwrap();
+		assert_eq!(start.to_notation(), TicTacToe::new().to_notation());
+		assert_eq!(moves, vec![(4, 0), (0, 4)]);
+
+		let notation = "OOOXX1X2/OOOXX1X2/OO1XX4/9/9/9/9/9/9 OO7 - O";
+		let args: Vec<&str> = Some("notation").into_iter().chain(notation.split(' ')).collect();
+		let (start, moves) = parse_position(&args, Rules::default()).unwrap();
+		assert_eq!(start.to_notation(), notation);
+		assert!(moves.is_empty());
+	}
+
+	#[test]
+	fn bad_positions_say_what_was_wrong() {
+		let rules = Rules::default();
+		assert_eq!(parse_position(&[], rules).err(), Some("position must start with `startpos` or `notation`".to_owned()));
+		assert_eq!(parse_position(&["notation", "9/9/9"], rules).err(), Some("position must start with `startpos` or `notation`".to_owned()));
+		assert_eq!(parse_position(&["startpos", "40"], rules).err(), Some("expected `moves`, found `40`".to_owned()));
+		// after 40 the next move has to be in board 0
+		assert_eq!(parse_position(&["startpos", "moves", "40", "48"], rules).err(), Some("illegal move 48".to_owned()));
+		assert_eq!(parse_position(&["startpos", "moves", "4"], rules).err(), Some("illegal move 4".to_owned()));
+		assert!(parse_position(&["notation", "9/9/9", "9", "-", "X"], rules).is_err());
+	}
+
+	#[test]
+	fn positions_are_played_with_the_rules_given() {
+		// O is free to move, but board 0 is already won
+		let won = ["notation", "XXXOO4/9/9/9/9/9/9/9/9", "X8", "-", "O", "moves", "05"];
+		assert_eq!(parse_position(&won, Rules::default()).err(), Some("illegal move 05".to_owned()));
+
+		let any_empty_cell = Rules { free_move: FreeMove::AnyEmptyCell, ..Rules::default() };
+		let (start, moves) = parse_position(&won, any_empty_cell).unwrap();
+		assert_eq!(start.rules(), any_empty_cell);
+		assert_eq!(moves, vec![(0, 5)]);
+		assert_eq!(parse_position(&["startpos"], any_empty_cell).unwrap().0.rules(), any_empty_cell);
+	}
+
+	#[test]
+	fn go_parameters_become_limits() {
+		let limits = parse_limits(&["movetime", "250", "sims", "1000", "nodes", "50", "depth", "3"], &Side::default()).unwrap();
+		assert_eq!(limits, Limits {
+			time: Some(Duration::from_millis(250)),
+			sims: Some(1000),
+			nodes: Some(50),
+			depth: Some(3),
+		});
+
+		assert_eq!(parse_limits(&["infinite"], &Side::default()), Ok(Limits::default()));
+		assert_eq!(parse_limits(&[], &Side::default()), Ok(Side::default().turn_limits()));
+	}
+
+	#[test]
+	fn bad_go_parameters_say_what_was_wrong() {
+		assert_eq!(parse_limits(&["sims"], &Side::default()), Err("sims expects a number".to_owned()));
+		assert_eq!(parse_limits(&["movetime", "soon"], &Side::default()), Err("movetime expects a number".to_owned()));
+		assert_eq!(parse_limits(&["mate", "3"], &Side::default()), Err("unknown go parameter mate".to_owned()));
+	}
+
+	#[test]
+	fn ponder_is_the_only_option() {
+		assert_eq!(parse_option(&["name", "ponder", "value", "off"]), Ok(EngineOption::Ponder(Ponder::Off)));
+		assert_eq!(parse_option(&["name", "Ponder", "value", "on"]), Ok(EngineOption::Ponder(Ponder::On)));
+		assert_eq!(parse_option(&["name", "ponder", "value", "50"]),
+			Ok(EngineOption::Ponder(Ponder::Limited(Limits { sims: Some(50), ..Limits::default() }))));
+
+		assert_eq!(parse_option(&["name", "hash", "value", "64"]), Err("unknown option hash".to_owned()));
+		assert_eq!(parse_option(&["name", "ponder", "value", "sometimes"]), Err("ponder expects on, off or a number of simulations".to_owned()));
+		assert_eq!(parse_option(&["name", "ponder", "value", "on", "off"]), Err("ponder expects on, off or a number of simulations".to_owned()));
+		assert_eq!(parse_option(&["name", "ponder"]), Err("expected `setoption name NAME value VALUE`".to_owned()));
+		assert_eq!(parse_option(&["name", "ponder", "value"]), Err("expected `setoption name NAME value VALUE`".to_owned()));
+	}
+
+	#[test]
+	fn rules_take_standard_or_a_list_of_variants() {
+		assert_eq!(parse_option(&["name", "rules", "value", "standard"]), Ok(EngineOption::Rules(Rules::default())));
+		assert_eq!(parse_option(&["name", "rules", "value", "majority-tiebreak", "any-empty-cell"]), Ok(EngineOption::Rules(Rules {
+			majority_tiebreak: true,
+			free_move: FreeMove::AnyEmptyCell,
+			..Rules::default()
+		})));
+		assert_eq!(parse_option(&["name", "rules", "value", "gravity"]), Err("unknown rule variant gravity".to_owned()));
+	}
+}
@@ -0,0 +1,62 @@
+use std::io::Write;
+use std::process::{ Command, Stdio };
+
+/// Pipes `script` into the engine, closing stdin after it, and returns everything it printed.
+fn run_script(script: &str) -> String {
+	let mut engine = Command::new(env!("CARGO_BIN_EXE_tictactoe"))
+		.args(["--utttp", "--seed", "1"])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.expect("failed to start the engine");
+
+	engine.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+	let output = engine.wait_with_output().unwrap();
+	assert!(output.status.success());
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn a_piped_session_is_answered_in_order() {
+	let output = run_script("\
+utttp
+isready
+setoption name hash value 64
+position startpos moves 40 48
+position startpos moves 40 04
+go sims 200
+");
+	let lines: Vec<&str> = output.lines().collect();
+
+	assert_eq!(lines[0], format!("id name tictactoe {}", env!("CARGO_PKG_VERSION")));
+	assert_eq!(&lines[2..6], &[
+		"utttpok",
+		"readyok",
+		"info string unknown option hash",
+		"info string illegal move 48",
+	]);
+
+	// the search of the position after 40 04 has to play in board 4
+	let best = lines.iter().position(|line| line.starts_with("bestmove")).expect("no bestmove");
+	assert!(lines[best].starts_with("bestmove 4"), "{}", lines[best]);
+	assert!(lines[best - 1].starts_with("info ") && lines[best - 1].contains(" total_sims 200 "), "{}", lines[best - 1]);
+	assert_eq!(best, lines.len() - 1);
+}
+
+#[test]
+fn rules_set_over_the_protocol_apply_to_later_positions() {
+	// O is free to move, but board 0 is already won, which only `any-empty-cell` lets O play in
+	let output = run_script("\
+position notation XXXOO4/9/9/9/9/9/9/9/9 X8 - O moves 05
+setoption name rules value any-empty-cell
+position notation XXXOO4/9/9/9/9/9/9/9/9 X8 - O moves 05
+go sims 50
+");
+	let lines: Vec<&str> = output.lines().collect();
+
+	// only the position set before the rules is rejected
+	assert_eq!(lines[0], "info string illegal move 05");
+	assert_eq!(lines.iter().filter(|line| line.starts_with("info string")).count(), 1, "{}", output);
+	// 05 sends X to board 5
+	assert!(lines.last().unwrap().starts_with("bestmove 5"), "{}", output);
+}